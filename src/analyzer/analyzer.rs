@@ -0,0 +1,670 @@
+use crate::analyzer::assignment::Assignment;
+use crate::ast::{BinOp, CPart, Expr, Function, ItemKind, Module, Program, Stmt, StmtKind};
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::interpreter::checked_binary;
+use crate::parser::parser::parse_c_code;
+use crate::symbol_table::{Binding, ConstType, FunctionType, SymbolTable, VariableType, qualify};
+use crate::tokens::Span;
+use std::collections::HashMap;
+use std::mem;
+
+const BUILTINS: [&str; 11] = [
+    "putchar",
+    "exit",
+    "args",
+    "print",
+    "len",
+    "wrapping_add",
+    "wrapping_sub",
+    "wrapping_mul",
+    "checked_add",
+    "checked_sub",
+    "checked_mul",
+];
+
+pub struct Analyzer {
+    symbol_table: SymbolTable,
+    module: String,
+    require_main: bool,
+    line: usize,
+    span: Span,
+}
+
+impl Analyzer {
+    pub fn new(require_main: bool) -> Self {
+        Self {
+            symbol_table: SymbolTable::new(),
+            module: String::new(),
+            require_main,
+            line: 0,
+            span: Span::default(),
+        }
+    }
+
+    fn error(&self, code: &'static str, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(self.line, message)
+            .with_code(code)
+            .with_span(self.span)
+    }
+
+    pub fn symbol_table(&self) -> &SymbolTable {
+        &self.symbol_table
+    }
+
+    pub fn analyze(&mut self, program: &mut Program) -> Result<(), Box<Diagnostic>> {
+        self.collect_declarations(program)?;
+
+        for module in &mut program.modules {
+            self.check_module(module)
+                .map_err(|e| e.with_file(&module.path))?;
+        }
+
+        Ok(())
+    }
+
+    fn check_module(&mut self, module: &mut Module) -> Result<(), Box<Diagnostic>> {
+        self.module = module.name.clone();
+
+        for item in &mut module.items {
+            (self.line, self.span) = (item.line, item.span);
+            match &mut item.kind {
+                ItemKind::FUNCTION(func) => {
+                    func.name = qualify(&self.module, &func.name);
+                    self.symbol_table.clear_vars();
+                    for ((param, param_type), span) in func.params.iter().zip(&func.param_spans) {
+                        if self.symbol_table.get_var(param).is_ok() {
+                            (self.line, self.span) = (span.line, *span);
+                            return Err(self.error(
+                                "E0031",
+                                format!(
+                                    "Parameter '{}' is declared more than once in function '{}'",
+                                    param, func.name
+                                ),
+                            ).into());
+                        }
+                        self.declare(param, param_type.clone(), *span, true);
+                    }
+                    self.symbol_table.mark_scope(&self.module, item.span.start);
+                    for stmt in &mut func.body {
+                        self.check_stmt(stmt, &func.ret_type)?;
+                    }
+                    Assignment::new().check(&func.body)?;
+                }
+                ItemKind::CONST(constant) => {
+                    constant.name = qualify(&self.module, &constant.name);
+                }
+                ItemKind::IMPORT(_) => {}
+                ItemKind::CCODE(parts) => {
+                    self.symbol_table.clear_vars();
+                    self.check_c_code(parts)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn collect_declarations(&mut self, program: &Program) -> Result<(), Box<Diagnostic>> {
+        let mut definitions: HashMap<String, usize> = HashMap::new();
+
+        for module in &program.modules {
+            for imported in &module.imports {
+                self.symbol_table
+                    .add_import(module.name.clone(), imported.clone());
+            }
+
+            self.declare_module(module, &mut definitions)
+                .map_err(|e| e.with_file(&module.path))?;
+        }
+
+        if self.require_main && self.symbol_table.get_func("main").is_err() {
+            let error = Diagnostic::global(
+                "No main function found, every executable needs a 'main :: int' function",
+            )
+            .with_code("E0033");
+            let error = match program.modules.last() {
+                Some(root) => error.with_file(&root.path),
+                None => error,
+            };
+            return Err(error.into());
+        }
+
+        Ok(())
+    }
+
+    fn declare_module(
+        &mut self,
+        module: &Module,
+        definitions: &mut HashMap<String, usize>,
+    ) -> Result<(), Box<Diagnostic>> {
+        for item in &module.items {
+            let (name, kind) = match &item.kind {
+                ItemKind::FUNCTION(func) => (&func.name, "Function"),
+                ItemKind::CONST(constant) => (&constant.name, "Constant"),
+                ItemKind::CCODE(_) | ItemKind::IMPORT(_) => continue,
+            };
+
+            (self.line, self.span) = (item.line, item.span);
+            let qualified = qualify(&module.name, name);
+            if let Some(previous) = definitions.insert(qualified.clone(), item.line) {
+                return Err(self
+                    .error("E0031", format!("{} '{}' is already defined", kind, name))
+                    .note(
+                        previous,
+                        format!("Previous definition of '{}' is here", name),
+                    )
+                    .into());
+            }
+
+            match &item.kind {
+                ItemKind::FUNCTION(func) => {
+                    if BUILTINS.contains(&qualified.as_str()) {
+                        return Err(self
+                            .error(
+                                "E0032",
+                                format!("Function '{}' conflicts with a builtin function", name),
+                            )
+                            .into());
+                    }
+                    if func.public {
+                        self.symbol_table.set_public(qualified.clone());
+                    }
+                    if module.name.is_empty() && func.name == "main" {
+                        self.check_main(func)?;
+                    }
+                    self.symbol_table
+                        .set_func_params(qualified.clone(), func.params.clone());
+                    self.symbol_table.set_func(qualified, func.ret_type.clone());
+                }
+                ItemKind::CONST(constant) => {
+                    if constant.public {
+                        self.symbol_table.set_public(qualified.clone());
+                    }
+                    self.symbol_table
+                        .set_const(qualified, constant.value.clone());
+                }
+                ItemKind::CCODE(_) | ItemKind::IMPORT(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_main(&self, func: &Function) -> Result<(), Box<Diagnostic>> {
+        if let FunctionType::STR = func.ret_type {
+            return Err(self
+                .error("E0020", "Return type of main must be int got str")
+                .into());
+        }
+
+        let valid = match func.params.as_slice() {
+            [] => true,
+            [(_, param_type)] => *param_type == VariableType::INT32,
+            _ => false,
+        };
+        if !valid {
+            return Err(self
+                .error(
+                    "E0034",
+                    format!(
+                        "main takes either no parameters or a single 'argc: int' parameter, got {}",
+                        func.params.len()
+                    ),
+                )
+                .into());
+        }
+
+        Ok(())
+    }
+
+    fn resolve(&self, path: &str) -> Result<String, Box<Diagnostic>> {
+        let Some((module, name)) = path.split_once("::") else {
+            return Ok(qualify(&self.module, path));
+        };
+
+        if module == self.module {
+            return Ok(path.to_string());
+        }
+
+        if !self.symbol_table.has_import(&self.module, module) {
+            return Err(self
+                .error("E0036", format!("Module '{}' is not imported", module))
+                .into());
+        }
+
+        let exists =
+            self.symbol_table.get_func(path).is_ok() || self.symbol_table.get_const(path).is_ok();
+        if exists && !self.symbol_table.is_public(path) {
+            return Err(self
+                .error(
+                    "E0037",
+                    format!("'{}' is private to module '{}'", name, module),
+                )
+                .into());
+        }
+
+        Ok(path.to_string())
+    }
+
+    fn declare(&mut self, name: &str, var_type: VariableType, span: Span, param: bool) {
+        let binding = Binding {
+            name: name.to_string(),
+            module: self.module.clone(),
+            span,
+            var_type,
+            param,
+            reads: 0,
+        };
+        self.symbol_table.set_var(binding);
+    }
+
+    fn check_stmt(
+        &mut self,
+        stmt: &mut Stmt,
+        func_ret_type: &FunctionType,
+    ) -> Result<(), Box<Diagnostic>> {
+        (self.line, self.span) = (stmt.line, stmt.span);
+        self.symbol_table.mark_scope(&self.module, stmt.span.start);
+        match &mut stmt.kind {
+            StmtKind::LET(name, var_type, value) => {
+                if self.symbol_table.get_var(name).is_ok() {
+                    return Err(self
+                        .error("E0031", format!("Variable '{}' is already defined", name))
+                        .into());
+                }
+                let value_type = match (var_type.as_ref(), value) {
+                    (Some(var_type), Some(value)) => {
+                        self.expect_type(value, var_type)?;
+                        var_type.clone()
+                    }
+                    (Some(var_type), None) => var_type.clone(),
+                    (None, Some(value)) => self.check_value(value)?,
+                    (None, None) => unreachable!(),
+                };
+                self.declare(name, value_type.clone(), stmt.span, false);
+                *var_type = Some(value_type);
+            }
+            StmtKind::ASSIGN(name, value) => {
+                let var_type = match self.symbol_table.get_var(name) {
+                    Ok(t) => t,
+                    Err(e) => {
+                        let is_const = self
+                            .resolve(name)
+                            .is_ok_and(|n| self.symbol_table.get_const(&n).is_ok());
+                        if is_const {
+                            return Err(self
+                                .error("E0042", format!("Cannot assign to constant '{}'", name))
+                                .into());
+                        }
+                        return Err(self.error("E0035", e).into());
+                    }
+                };
+                self.expect_type(value, &var_type)?;
+            }
+            StmtKind::EXPR(expr) => {
+                self.check_expr(expr)?;
+            }
+            StmtKind::RETURN(value) => {
+                match (func_ret_type.value_type(), value) {
+                    (Some(ret_type), Some(value)) => self.expect_type(value, &ret_type)?,
+                    (Some(ret_type), None) => {
+                        return Err(self.error(
+                        "E0041",
+                        format!(
+                            "Expected a value of type {} at the end of a {} returning function",
+                            ret_type, ret_type
+                        ),
+                    ).into());
+                    }
+                    (None, Some(_)) => {
+                        return Err(self
+                            .error("E0041", "Cannot return a value from a void function")
+                            .into());
+                    }
+                    (None, None) => {}
+                }
+            }
+            StmtKind::IF(cond, then, otherwise) => {
+                self.expect_type(cond, &VariableType::INT32)?;
+                self.check_block(then, func_ret_type)?;
+                self.check_block(otherwise, func_ret_type)?;
+            }
+            StmtKind::WHILE(cond, body) => {
+                self.expect_type(cond, &VariableType::INT32)?;
+                self.check_block(body, func_ret_type)?;
+            }
+            StmtKind::CCODE(parts) => self.check_c_code(parts)?,
+        }
+
+        Ok(())
+    }
+
+    fn check_block(
+        &mut self,
+        body: &mut [Stmt],
+        func_ret_type: &FunctionType,
+    ) -> Result<(), Box<Diagnostic>> {
+        self.symbol_table.push_scope();
+        for stmt in body {
+            self.check_stmt(stmt, func_ret_type)?;
+        }
+        self.symbol_table.pop_scope();
+        Ok(())
+    }
+
+    fn expect_type(
+        &mut self,
+        expr: &mut Expr,
+        expected: &VariableType,
+    ) -> Result<(), Box<Diagnostic>> {
+        let found = self.check_value(expr)?;
+        if found != *expected {
+            return Err(self
+                .error(
+                    "E0038",
+                    format!("Expected a value of type {} but got {}", expected, found),
+                )
+                .into());
+        }
+        Ok(())
+    }
+
+    fn check_value(&mut self, expr: &mut Expr) -> Result<VariableType, Box<Diagnostic>> {
+        match self.check_expr(expr)? {
+            Some(t) => Ok(t),
+            None => {
+                let name = match expr {
+                    Expr::CALL(name, _) | Expr::BUILTIN(name, _) => name.clone(),
+                    _ => String::new(),
+                };
+                Err(self
+                    .error(
+                        "E0039",
+                        format!("Function '{}' returns void, expected a value", name),
+                    )
+                    .into())
+            }
+        }
+    }
+
+    fn check_expr(&mut self, expr: &mut Expr) -> Result<Option<VariableType>, Box<Diagnostic>> {
+        let value_type = match expr {
+            Expr::NUMBER(_) | Expr::CONST(_) => Some(VariableType::INT32),
+            Expr::STRING(_) => Some(VariableType::STR),
+            Expr::VAR(name) => {
+                self.symbol_table.read_var(name);
+                self.symbol_table.get_var(name).ok()
+            }
+            Expr::IDENT(path) => {
+                if let Ok(var_type) = self.symbol_table.get_var(path) {
+                    self.symbol_table.read_var(path);
+                    *expr = Expr::VAR(path.clone());
+                    return Ok(Some(var_type));
+                }
+
+                let name = self.resolve(path)?;
+                match self.symbol_table.get_const(&name) {
+                    Ok(ConstType::INT32(_)) => {
+                        *expr = Expr::CONST(name);
+                        Some(VariableType::INT32)
+                    }
+                    Ok(ConstType::STRING(_)) => {
+                        return Err(self.error(
+                            "E0043",
+                            format!(
+                                "Constant '{}' holds c code and can only be used with c_comp_append",
+                                path
+                            ),
+                        ).into());
+                    }
+                    Err(_) => {
+                        return Err(self
+                            .error("E0035", format!("Variable '{}' not in scope", path))
+                            .into());
+                    }
+                }
+            }
+            Expr::INDEX(base, index) => {
+                let base_type = self.check_value(base)?;
+                self.expect_type(index, &VariableType::INT32)?;
+
+                let (builtin, elem_type) = match base_type {
+                    VariableType::SLICE(inner) => ("slice_at", *inner),
+                    VariableType::STR => ("str_at", VariableType::INT32),
+                    VariableType::INT32 => {
+                        return Err(self
+                            .error("E0038", "Cannot index into a value of type int")
+                            .into());
+                    }
+                };
+
+                let base = mem::replace(base.as_mut(), Expr::NUMBER(0));
+                let index = mem::replace(index.as_mut(), Expr::NUMBER(0));
+                *expr = Expr::BUILTIN(builtin.to_string(), vec![base, index]);
+                Some(elem_type)
+            }
+            Expr::BINARY(op, lhs, rhs) => {
+                self.expect_type(lhs, &VariableType::INT32)?;
+                self.expect_type(rhs, &VariableType::INT32)?;
+                if let (Some(a), Some(b)) = (self.const_int(lhs), self.const_int(rhs))
+                    && checked_binary(*op, a, b).is_none()
+                {
+                    let (code, problem) = if b == 0 {
+                        ("E0046", "divides by zero")
+                    } else {
+                        ("E0045", "overflows an int")
+                    };
+                    return Err(self
+                        .error(
+                            code,
+                            format!(
+                                "Constant expression {}: {} {} {}",
+                                problem,
+                                a,
+                                op.symbol(),
+                                b
+                            ),
+                        )
+                        .into());
+                }
+                Some(VariableType::INT32)
+            }
+            Expr::NEG(value) => {
+                self.expect_type(value, &VariableType::INT32)?;
+                if let Some(n) = self.const_int(value)
+                    && n.checked_neg().is_none()
+                {
+                    return Err(self
+                        .error(
+                            "E0045",
+                            format!("Constant expression overflows an int: -({})", n),
+                        )
+                        .into());
+                }
+                Some(VariableType::INT32)
+            }
+            Expr::BUILTIN(..) => None,
+            Expr::CALL(path, args) if BUILTINS.contains(&path.as_str()) => {
+                let (builtin, ret_type) = self.check_builtin(path, args)?;
+                let args = mem::take(args);
+                *expr = Expr::BUILTIN(builtin, args);
+                ret_type
+            }
+            Expr::CALL(path, args) => {
+                let name = self.resolve(path)?;
+                let func_type = match self.symbol_table.get_func(&name) {
+                    Ok(t) => t,
+                    Err(_) => {
+                        return Err(self
+                            .error("E0035", format!("Unknown function '{}'", path))
+                            .into());
+                    }
+                };
+
+                let params = self.symbol_table.get_func_params(&name);
+                if args.len() != params.len() {
+                    return Err(self
+                        .error(
+                            "E0040",
+                            format!(
+                                "Function '{}' takes {} argument(s) but {} were given",
+                                path,
+                                params.len(),
+                                args.len()
+                            ),
+                        )
+                        .into());
+                }
+                for (arg, (_, param_type)) in args.iter_mut().zip(&params) {
+                    self.expect_type(arg, param_type)?;
+                }
+
+                self.symbol_table.call_func(&name);
+                *path = name;
+                func_type.value_type()
+            }
+        };
+        Ok(value_type)
+    }
+
+    fn const_int(&self, expr: &Expr) -> Option<i32> {
+        match expr {
+            Expr::NUMBER(n) => Some(*n),
+            Expr::CONST(name) => match self.symbol_table.get_const(name) {
+                Ok(ConstType::INT32(n)) => Some(n),
+                _ => None,
+            },
+            Expr::NEG(value) => self.const_int(value)?.checked_neg(),
+            Expr::BINARY(op, lhs, rhs) => {
+                checked_binary(*op, self.const_int(lhs)?, self.const_int(rhs)?)
+            }
+            _ => None,
+        }
+    }
+
+    fn check_builtin(
+        &mut self,
+        name: &str,
+        args: &mut [Expr],
+    ) -> Result<(String, Option<VariableType>), Box<Diagnostic>> {
+        let expected = match name {
+            "args" => 0,
+            _ if BinOp::intrinsic(name).is_some() => 2,
+            _ => 1,
+        };
+        if args.len() != expected {
+            return Err(self
+                .error(
+                    "E0040",
+                    format!(
+                        "Function '{}' takes {} argument(s) but {} were given",
+                        name,
+                        expected,
+                        args.len()
+                    ),
+                )
+                .into());
+        }
+
+        let builtin = match name {
+            "putchar" => {
+                self.expect_type(&mut args[0], &VariableType::INT32)?;
+                ("putchar".to_string(), Some(VariableType::INT32))
+            }
+            "exit" => {
+                self.expect_type(&mut args[0], &VariableType::INT32)?;
+                ("exit".to_string(), None)
+            }
+            "args" => (
+                "args".to_string(),
+                Some(VariableType::SLICE(Box::new(VariableType::STR))),
+            ),
+            "print" => {
+                self.expect_type(&mut args[0], &VariableType::STR)?;
+                ("print".to_string(), None)
+            }
+            "len" => match self.check_value(&mut args[0])? {
+                VariableType::STR => ("str_len".to_string(), Some(VariableType::INT32)),
+                VariableType::SLICE(_) => ("slice_len".to_string(), Some(VariableType::INT32)),
+                VariableType::INT32 => {
+                    return Err(self
+                        .error(
+                            "E0038",
+                            "Function 'len' expects a str or a slice but got int",
+                        )
+                        .into());
+                }
+            },
+            _ => {
+                self.expect_type(&mut args[0], &VariableType::INT32)?;
+                self.expect_type(&mut args[1], &VariableType::INT32)?;
+                (name.to_string(), Some(VariableType::INT32))
+            }
+        };
+        Ok(builtin)
+    }
+
+    fn check_c_code(&mut self, parts: &mut Vec<CPart>) -> Result<(), Box<Diagnostic>> {
+        let mut resolved = Vec::new();
+
+        for part in parts.drain(..) {
+            match part {
+                part @ (CPart::TEXT(_) | CPart::ITEM(_)) => resolved.push(part),
+                CPart::IDENT(path) => resolved.push(self.resolve_c_ident(&path)?),
+                CPart::CONST(path) => {
+                    let name = self.resolve(&path)?;
+                    match self.symbol_table.get_const(&name) {
+                        Ok(ConstType::STRING(s)) => {
+                            let code = parse_c_code(&s).map_err(|e| self.error("E0016", e))?;
+                            for part in code {
+                                match part {
+                                    CPart::IDENT(path) => {
+                                        resolved.push(self.resolve_c_ident(&path)?)
+                                    }
+                                    part => resolved.push(part),
+                                }
+                            }
+                        }
+                        Ok(ConstType::INT32(v)) => resolved.push(CPart::TEXT(v.to_string())),
+                        Err(e) => {
+                            if self.symbol_table.get_var(&path).is_ok() {
+                                return Err(self.error(
+                                    "E0044",
+                                    format!(
+                                        "c_comp_append only accepts constants, use \"${{{}}}\" to insert the name of variable '{}'",
+                                        path, path
+                                    ),
+                                ).into());
+                            }
+                            return Err(self.error("E0035", e).into());
+                        }
+                    }
+                }
+            }
+        }
+
+        *parts = resolved;
+        Ok(())
+    }
+
+    fn resolve_c_ident(&mut self, path: &str) -> Result<CPart, Box<Diagnostic>> {
+        if self.symbol_table.get_var(path).is_ok() {
+            self.symbol_table.read_var(path);
+            return Ok(CPart::IDENT(path.to_string()));
+        }
+
+        let name = self.resolve(path)?;
+        if self.symbol_table.get_func(&name).is_ok() {
+            self.symbol_table.call_func(&name);
+            return Ok(CPart::ITEM(name));
+        }
+
+        match self.symbol_table.get_const(&name) {
+            Ok(ConstType::INT32(_)) => Ok(CPart::ITEM(name)),
+            Ok(ConstType::STRING(s)) => Ok(CPart::TEXT(s)),
+            Err(_) => Err(self
+                .error("E0035", format!("Unknown identifier '{}' in c code", path))
+                .into()),
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::ast::{CPart, Expr, Stmt, StmtKind};
+use crate::diagnostic::Diagnostic;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::mem;
+
+type Path = Vec<(usize, String)>;
+
+pub struct Assignment {
+    unassigned: Option<HashMap<String, Path>>,
+    scopes: Vec<Vec<String>>,
+}
+
+impl Assignment {
+    pub fn new() -> Self {
+        Self {
+            unassigned: Some(HashMap::new()),
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn check(mut self, body: &[Stmt]) -> Result<(), Box<Diagnostic>> {
+        self.block(body)
+    }
+
+    fn block(&mut self, body: &[Stmt]) -> Result<(), Box<Diagnostic>> {
+        self.scopes.push(Vec::new());
+        for stmt in body {
+            self.stmt(stmt)?;
+        }
+
+        let declared = self.scopes.pop().unwrap();
+        if let Some(unassigned) = &mut self.unassigned {
+            for name in declared {
+                unassigned.remove(&name);
+            }
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), Box<Diagnostic>> {
+        let line = stmt.line;
+        match &stmt.kind {
+            StmtKind::LET(name, _, value) => {
+                match value {
+                    Some(value) => self.expr(value, stmt)?,
+                    None => {
+                        if let Some(unassigned) = &mut self.unassigned {
+                            let path = vec![(
+                                line,
+                                format!("'{}' is declared here without a value", name),
+                            )];
+                            unassigned.insert(name.clone(), path);
+                        }
+                    }
+                }
+                self.scopes.last_mut().unwrap().push(name.clone());
+            }
+            StmtKind::ASSIGN(name, value) => {
+                self.expr(value, stmt)?;
+                self.assign(name);
+            }
+            StmtKind::EXPR(expr) => {
+                self.expr(expr, stmt)?;
+                if matches!(expr, Expr::BUILTIN(name, _) if name == "exit") {
+                    self.unassigned = None;
+                }
+            }
+            StmtKind::RETURN(value) => {
+                if let Some(value) = value {
+                    self.expr(value, stmt)?;
+                }
+                self.unassigned = None;
+            }
+            StmtKind::IF(cond, then, otherwise) => {
+                self.expr(cond, stmt)?;
+                let before = self.unassigned.clone();
+                self.block(then)?;
+                let after_then = mem::replace(&mut self.unassigned, before);
+                self.block(otherwise)?;
+
+                self.unassigned = match (after_then, self.unassigned.take()) {
+                    (None, state) | (state, None) => state,
+                    (Some(mut after_then), Some(after_otherwise)) => {
+                        for (name, path) in &mut after_then {
+                            if !after_otherwise.contains_key(name) {
+                                path.push((
+                                    line,
+                                    format!(
+                                        "If this condition is true, '{}' is not assigned",
+                                        name
+                                    ),
+                                ));
+                            }
+                        }
+                        for (name, mut path) in after_otherwise {
+                            if let Entry::Vacant(entry) = after_then.entry(name) {
+                                path.push((
+                                    line,
+                                    format!(
+                                        "If this condition is false, '{}' is not assigned",
+                                        entry.key()
+                                    ),
+                                ));
+                                entry.insert(path);
+                            }
+                        }
+                        Some(after_then)
+                    }
+                };
+            }
+            StmtKind::WHILE(cond, body) => {
+                self.expr(cond, stmt)?;
+                let mut before = self.unassigned.clone();
+                self.block(body)?;
+
+                if let Some(before) = &mut before {
+                    for (name, path) in before {
+                        if self
+                            .unassigned
+                            .as_ref()
+                            .is_none_or(|after| !after.contains_key(name))
+                        {
+                            path.push((
+                                line,
+                                format!("If this loop never runs, '{}' is not assigned", name),
+                            ));
+                        }
+                    }
+                }
+                self.unassigned = before;
+            }
+            StmtKind::CCODE(parts) => {
+                for part in parts {
+                    if let CPart::IDENT(name) = part {
+                        self.assign(name);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, name: &str) {
+        if let Some(unassigned) = &mut self.unassigned {
+            unassigned.remove(name);
+        }
+    }
+
+    fn expr(&self, expr: &Expr, stmt: &Stmt) -> Result<(), Box<Diagnostic>> {
+        match expr {
+            Expr::VAR(name) => {
+                if let Some(path) = self.unassigned.as_ref().and_then(|u| u.get(name)) {
+                    let mut error = Diagnostic::new(
+                        stmt.line,
+                        format!("Use of possibly uninitialized variable '{}'", name),
+                    )
+                    .with_code("E0029")
+                    .with_span(stmt.span);
+                    for (line, message) in path {
+                        error = error.note(*line, message.clone());
+                    }
+                    return Err(error.into());
+                }
+            }
+            Expr::CALL(_, args) | Expr::BUILTIN(_, args) => {
+                for arg in args {
+                    self.expr(arg, stmt)?;
+                }
+            }
+            Expr::INDEX(lhs, rhs) | Expr::BINARY(_, lhs, rhs) => {
+                self.expr(lhs, stmt)?;
+                self.expr(rhs, stmt)?;
+            }
+            Expr::NEG(value) => self.expr(value, stmt)?,
+            Expr::NUMBER(_) | Expr::STRING(_) | Expr::IDENT(_) | Expr::CONST(_) => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyzer::analyzer::Analyzer;
+    use crate::diagnostic::Diagnostic;
+    use crate::parser::parser::Parser;
+    use std::path::PathBuf;
+
+    fn check(body: &str) -> Result<(), Box<Diagnostic>> {
+        let source = format!("main :: int(argc: int) {{\n    let x: int;\n{}}}\n", body);
+        let mut program = Parser::new(source, PathBuf::from("test.ed")).parse()?;
+        Analyzer::new(true).analyze(&mut program)
+    }
+
+    fn error(body: &str, line: usize) -> Vec<(usize, String)> {
+        let error = check(body).expect_err("x is read before it is assigned");
+        assert_eq!(error.code, Some("E0029"));
+        assert_eq!(error.line, Some(line));
+        assert_eq!(error.message, "Use of possibly uninitialized variable 'x'");
+        error
+            .notes
+            .into_iter()
+            .map(|note| (note.line, note.message))
+            .collect()
+    }
+
+    fn declared() -> (usize, String) {
+        (2, "'x' is declared here without a value".to_string())
+    }
+
+    #[test]
+    fn never_assigned() {
+        assert_eq!(error("    return x;\n", 3), vec![declared()]);
+    }
+
+    #[test]
+    fn both_branches() {
+        let body = "    if argc > 1 {\n        x = 1;\n    } else {\n        x = 2;\n    }\n    return x;\n";
+        assert_eq!(check(body), Ok(()));
+    }
+
+    #[test]
+    fn one_branch() {
+        let body = "    if argc > 1 {\n        x = 1;\n    }\n    return x;\n";
+        let path = (
+            3,
+            "If this condition is false, 'x' is not assigned".to_string(),
+        );
+        assert_eq!(error(body, 6), vec![declared(), path]);
+
+        let body = "    if argc > 1 {\n        argc = 0;\n    } else {\n        x = 2;\n    }\n    return x;\n";
+        let path = (
+            3,
+            "If this condition is true, 'x' is not assigned".to_string(),
+        );
+        assert_eq!(error(body, 8), vec![declared(), path]);
+    }
+
+    #[test]
+    fn nested_branches() {
+        let body = "    if argc > 1 {\n        if argc > 2 {\n            x = 1;\n        }\n    } else {\n        x = 2;\n    }\n    return x;\n";
+        let path = vec![
+            declared(),
+            (
+                4,
+                "If this condition is false, 'x' is not assigned".to_string(),
+            ),
+            (
+                3,
+                "If this condition is true, 'x' is not assigned".to_string(),
+            ),
+        ];
+        assert_eq!(error(body, 10), path);
+    }
+
+    #[test]
+    fn loop_may_not_run() {
+        let body = "    while argc > 1 {\n        x = argc;\n        argc = argc - 1;\n    }\n    return x;\n";
+        let path = (
+            3,
+            "If this loop never runs, 'x' is not assigned".to_string(),
+        );
+        assert_eq!(error(body, 7), vec![declared(), path]);
+    }
+
+    #[test]
+    fn return_and_exit_end_a_path() {
+        let body = "    if argc > 1 {\n        x = 1;\n    } else {\n        return 0;\n    }\n    return x;\n";
+        assert_eq!(check(body), Ok(()));
+
+        let body = "    if argc > 1 {\n        exit(2);\n    } else {\n        x = 1;\n    }\n    return x;\n";
+        assert_eq!(check(body), Ok(()));
+    }
+
+    #[test]
+    fn c_block_assigns() {
+        let body = "    c {\n        ${x} = 4;\n    }\n    return x;\n";
+        assert_eq!(check(body), Ok(()));
+    }
+}
@@ -0,0 +1,338 @@
+use crate::symbol_table::{ConstType, FunctionType, VariableType, params_to_string};
+use crate::tokens::{Span, Trivia};
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinOp {
+    ADD,
+    SUB,
+    MUL,
+    DIV,
+    REM,
+    EQ,
+    NE,
+    LT,
+    LE,
+    GT,
+    GE,
+}
+
+impl BinOp {
+    pub fn intrinsic(name: &str) -> Option<BinOp> {
+        match name {
+            "wrapping_add" | "checked_add" => Some(BinOp::ADD),
+            "wrapping_sub" | "checked_sub" => Some(BinOp::SUB),
+            "wrapping_mul" | "checked_mul" => Some(BinOp::MUL),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinOp::ADD => "+",
+            BinOp::SUB => "-",
+            BinOp::MUL => "*",
+            BinOp::DIV => "/",
+            BinOp::REM => "%",
+            BinOp::EQ => "==",
+            BinOp::NE => "!=",
+            BinOp::LT => "<",
+            BinOp::LE => "<=",
+            BinOp::GT => ">",
+            BinOp::GE => ">=",
+        }
+    }
+
+    pub fn overflow_name(&self) -> &'static str {
+        match self {
+            BinOp::ADD => "add",
+            BinOp::SUB => "subtract",
+            BinOp::MUL => "multiply",
+            BinOp::DIV => "divide",
+            BinOp::REM => "calculate the remainder",
+            _ => unreachable!("comparisons cannot overflow"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Expr {
+    NUMBER(i32),
+    STRING(String),
+    IDENT(String),
+    VAR(String),
+    CONST(String),
+    CALL(String, Vec<Expr>),
+    BUILTIN(String, Vec<Expr>),
+    INDEX(Box<Expr>, Box<Expr>),
+    BINARY(BinOp, Box<Expr>, Box<Expr>),
+    NEG(Box<Expr>),
+}
+
+#[derive(Clone, Debug)]
+pub enum CPart {
+    TEXT(String),
+    IDENT(String),
+    CONST(String),
+    ITEM(String),
+}
+
+#[derive(Clone, Debug)]
+pub enum StmtKind {
+    LET(String, Option<VariableType>, Option<Expr>),
+    ASSIGN(String, Expr),
+    EXPR(Expr),
+    RETURN(Option<Expr>),
+    IF(Expr, Vec<Stmt>, Vec<Stmt>),
+    WHILE(Expr, Vec<Stmt>),
+    CCODE(Vec<CPart>),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Comments {
+    pub leading: Vec<Trivia>,
+    pub trailing: Option<String>,
+    pub after: Vec<Trivia>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub line: usize,
+    pub span: Span,
+    pub allow: Vec<String>,
+    pub comments: Comments,
+}
+
+#[derive(Clone, Debug)]
+pub struct Function {
+    pub name: String,
+    pub public: bool,
+    pub ret_type: FunctionType,
+    pub params: Vec<(String, VariableType)>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+    pub param_spans: Vec<Span>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Const {
+    pub name: String,
+    pub public: bool,
+    pub value: ConstType,
+}
+
+#[derive(Clone, Debug)]
+pub enum Import {
+    FILE(String),
+    MOD(String),
+}
+
+#[derive(Clone, Debug)]
+pub enum ItemKind {
+    FUNCTION(Function),
+    CONST(Const),
+    CCODE(Vec<CPart>),
+    IMPORT(Import),
+}
+
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub line: usize,
+    pub span: Span,
+    pub allow: Vec<String>,
+    pub comments: Comments,
+}
+
+#[derive(Clone, Debug)]
+pub struct Module {
+    pub name: String,
+    pub path: PathBuf,
+    pub imports: Vec<String>,
+    pub items: Vec<Item>,
+    pub comments: Vec<Trivia>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Program {
+    pub modules: Vec<Module>,
+}
+
+fn indent(f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+    write!(f, "{}", "  ".repeat(depth))
+}
+
+fn write_allow(f: &mut fmt::Formatter, allow: &[String], depth: usize) -> fmt::Result {
+    if !allow.is_empty() {
+        indent(f, depth)?;
+        writeln!(f, "#[allow({})]", allow.join(", "))?;
+    }
+    Ok(())
+}
+
+fn write_c_code(f: &mut fmt::Formatter, parts: &[CPart], depth: usize) -> fmt::Result {
+    for part in parts {
+        indent(f, depth)?;
+        match part {
+            CPart::TEXT(text) => writeln!(f, "text {:?}", text)?,
+            CPart::IDENT(name) => writeln!(f, "ident {}", name)?,
+            CPart::CONST(name) => writeln!(f, "const {}", name)?,
+            CPart::ITEM(name) => writeln!(f, "item {}", name)?,
+        }
+    }
+    Ok(())
+}
+
+fn write_exprs(f: &mut fmt::Formatter, exprs: &[Expr], depth: usize) -> fmt::Result {
+    for expr in exprs {
+        write_expr(f, expr, depth)?;
+    }
+    Ok(())
+}
+
+fn write_expr(f: &mut fmt::Formatter, expr: &Expr, depth: usize) -> fmt::Result {
+    indent(f, depth)?;
+    match expr {
+        Expr::NUMBER(n) => writeln!(f, "number {}", n),
+        Expr::STRING(s) => writeln!(f, "string {:?}", s),
+        Expr::IDENT(name) => writeln!(f, "ident {}", name),
+        Expr::VAR(name) => writeln!(f, "var {}", name),
+        Expr::CONST(name) => writeln!(f, "const {}", name),
+        Expr::CALL(name, args) => {
+            writeln!(f, "call {}", name)?;
+            write_exprs(f, args, depth + 1)
+        }
+        Expr::BUILTIN(name, args) => {
+            writeln!(f, "builtin {}", name)?;
+            write_exprs(f, args, depth + 1)
+        }
+        Expr::INDEX(base, index) => {
+            writeln!(f, "index")?;
+            write_expr(f, base, depth + 1)?;
+            write_expr(f, index, depth + 1)
+        }
+        Expr::BINARY(op, lhs, rhs) => {
+            writeln!(f, "binary {}", op.symbol())?;
+            write_expr(f, lhs, depth + 1)?;
+            write_expr(f, rhs, depth + 1)
+        }
+        Expr::NEG(value) => {
+            writeln!(f, "neg")?;
+            write_expr(f, value, depth + 1)
+        }
+    }
+}
+
+fn write_stmts(f: &mut fmt::Formatter, body: &[Stmt], depth: usize) -> fmt::Result {
+    for stmt in body {
+        write_allow(f, &stmt.allow, depth)?;
+        indent(f, depth)?;
+        match &stmt.kind {
+            StmtKind::LET(name, var_type, value) => {
+                match var_type {
+                    Some(var_type) => {
+                        writeln!(f, "let {}: {} (line {})", name, var_type, stmt.line)?
+                    }
+                    None => writeln!(f, "let {} (line {})", name, stmt.line)?,
+                }
+                if let Some(value) = value {
+                    write_expr(f, value, depth + 1)?;
+                }
+            }
+            StmtKind::ASSIGN(name, value) => {
+                writeln!(f, "assign {} (line {})", name, stmt.line)?;
+                write_expr(f, value, depth + 1)?;
+            }
+            StmtKind::EXPR(expr) => {
+                writeln!(f, "expr (line {})", stmt.line)?;
+                write_expr(f, expr, depth + 1)?;
+            }
+            StmtKind::RETURN(value) => {
+                writeln!(f, "return (line {})", stmt.line)?;
+                if let Some(value) = value {
+                    write_expr(f, value, depth + 1)?;
+                }
+            }
+            StmtKind::IF(cond, then, otherwise) => {
+                writeln!(f, "if (line {})", stmt.line)?;
+                write_expr(f, cond, depth + 1)?;
+                indent(f, depth)?;
+                writeln!(f, "then")?;
+                write_stmts(f, then, depth + 1)?;
+                if !otherwise.is_empty() {
+                    indent(f, depth)?;
+                    writeln!(f, "else")?;
+                    write_stmts(f, otherwise, depth + 1)?;
+                }
+            }
+            StmtKind::WHILE(cond, body) => {
+                writeln!(f, "while (line {})", stmt.line)?;
+                write_expr(f, cond, depth + 1)?;
+                indent(f, depth)?;
+                writeln!(f, "do")?;
+                write_stmts(f, body, depth + 1)?;
+            }
+            StmtKind::CCODE(parts) => {
+                writeln!(f, "c (line {})", stmt.line)?;
+                write_c_code(f, parts, depth + 1)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for module in &self.modules {
+            let name = if module.name.is_empty() {
+                "<root>"
+            } else {
+                &module.name
+            };
+            writeln!(f, "module {} ({})", name, module.path.display())?;
+            for imported in &module.imports {
+                writeln!(f, "  import {}", imported)?;
+            }
+
+            for item in &module.items {
+                write_allow(f, &item.allow, 1)?;
+                let public = |public: bool| if public { "pub " } else { "" };
+                match &item.kind {
+                    ItemKind::FUNCTION(func) => {
+                        writeln!(
+                            f,
+                            "  {}fn {} :: {}({}) (line {})",
+                            public(func.public),
+                            func.name,
+                            func.ret_type,
+                            params_to_string(&func.params),
+                            item.line
+                        )?;
+                        write_stmts(f, &func.body, 2)?;
+                    }
+                    ItemKind::CONST(constant) => writeln!(
+                        f,
+                        "  {}const {} = {} (line {})",
+                        public(constant.public),
+                        constant.name,
+                        constant.value,
+                        item.line
+                    )?,
+                    ItemKind::CCODE(parts) => {
+                        writeln!(f, "  c (line {})", item.line)?;
+                        write_c_code(f, parts, 2)?;
+                    }
+                    ItemKind::IMPORT(Import::FILE(path)) => {
+                        writeln!(f, "  import {:?} (line {})", path, item.line)?
+                    }
+                    ItemKind::IMPORT(Import::MOD(name)) => {
+                        writeln!(f, "  mod {} (line {})", name, item.line)?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
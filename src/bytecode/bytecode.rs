@@ -0,0 +1,826 @@
+use crate::ast::BinOp;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+const MAGIC: &[u8; 4] = b"EDBC";
+const VERSION: u8 = 2;
+const MAX_LOCALS: u32 = 1 << 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    INT(i32),
+    STR(u32),
+    LOAD(u32),
+    STORE(u32),
+    CALL(u32),
+    POP,
+    RET,
+    END,
+    PUTCHAR,
+    EXIT,
+    ARGS,
+    PRINT,
+    STRLEN,
+    SLICELEN,
+    STRAT,
+    SLICEAT,
+    ADD,
+    SUB,
+    MUL,
+    DIV,
+    REM,
+    NEG,
+    JMP(u32),
+    JZ(u32),
+    EQ,
+    NE,
+    LT,
+    LE,
+    GT,
+    GE,
+    WADD,
+    WSUB,
+    WMUL,
+    WDIV,
+    WREM,
+    WNEG,
+}
+
+impl Op {
+    fn opcode(&self) -> u8 {
+        match self {
+            Op::INT(_) => 0,
+            Op::STR(_) => 1,
+            Op::LOAD(_) => 2,
+            Op::STORE(_) => 3,
+            Op::CALL(_) => 4,
+            Op::POP => 5,
+            Op::RET => 6,
+            Op::END => 7,
+            Op::PUTCHAR => 8,
+            Op::EXIT => 9,
+            Op::ARGS => 10,
+            Op::PRINT => 11,
+            Op::STRLEN => 12,
+            Op::SLICELEN => 13,
+            Op::STRAT => 14,
+            Op::SLICEAT => 15,
+            Op::ADD => 16,
+            Op::SUB => 17,
+            Op::MUL => 18,
+            Op::DIV => 19,
+            Op::REM => 20,
+            Op::NEG => 21,
+            Op::JMP(_) => 22,
+            Op::JZ(_) => 23,
+            Op::EQ => 24,
+            Op::NE => 25,
+            Op::LT => 26,
+            Op::LE => 27,
+            Op::GT => 28,
+            Op::GE => 29,
+            Op::WADD => 30,
+            Op::WSUB => 31,
+            Op::WMUL => 32,
+            Op::WDIV => 33,
+            Op::WREM => 34,
+            Op::WNEG => 35,
+        }
+    }
+
+    pub fn binop(&self) -> Option<BinOp> {
+        match self {
+            Op::ADD | Op::WADD => Some(BinOp::ADD),
+            Op::SUB | Op::WSUB => Some(BinOp::SUB),
+            Op::MUL | Op::WMUL => Some(BinOp::MUL),
+            Op::DIV | Op::WDIV => Some(BinOp::DIV),
+            Op::REM | Op::WREM => Some(BinOp::REM),
+            Op::EQ => Some(BinOp::EQ),
+            Op::NE => Some(BinOp::NE),
+            Op::LT => Some(BinOp::LT),
+            Op::LE => Some(BinOp::LE),
+            Op::GT => Some(BinOp::GT),
+            Op::GE => Some(BinOp::GE),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self, code: &mut Vec<u8>) {
+        code.push(self.opcode());
+        match self {
+            Op::INT(n) => code.extend_from_slice(&n.to_le_bytes()),
+            Op::STR(n) | Op::LOAD(n) | Op::STORE(n) | Op::CALL(n) | Op::JMP(n) | Op::JZ(n) => {
+                code.extend_from_slice(&n.to_le_bytes())
+            }
+            _ => {}
+        }
+    }
+
+    pub fn decode(code: &[u8], pc: usize) -> Option<(Op, usize)> {
+        let opcode = *code.get(pc)?;
+        let operand = || -> Option<[u8; 4]> { code.get(pc + 1..pc + 5)?.try_into().ok() };
+
+        let op = match opcode {
+            0 => Op::INT(i32::from_le_bytes(operand()?)),
+            1 => Op::STR(u32::from_le_bytes(operand()?)),
+            2 => Op::LOAD(u32::from_le_bytes(operand()?)),
+            3 => Op::STORE(u32::from_le_bytes(operand()?)),
+            4 => Op::CALL(u32::from_le_bytes(operand()?)),
+            5 => Op::POP,
+            6 => Op::RET,
+            7 => Op::END,
+            8 => Op::PUTCHAR,
+            9 => Op::EXIT,
+            10 => Op::ARGS,
+            11 => Op::PRINT,
+            12 => Op::STRLEN,
+            13 => Op::SLICELEN,
+            14 => Op::STRAT,
+            15 => Op::SLICEAT,
+            16 => Op::ADD,
+            17 => Op::SUB,
+            18 => Op::MUL,
+            19 => Op::DIV,
+            20 => Op::REM,
+            21 => Op::NEG,
+            22 => Op::JMP(u32::from_le_bytes(operand()?)),
+            23 => Op::JZ(u32::from_le_bytes(operand()?)),
+            24 => Op::EQ,
+            25 => Op::NE,
+            26 => Op::LT,
+            27 => Op::LE,
+            28 => Op::GT,
+            29 => Op::GE,
+            30 => Op::WADD,
+            31 => Op::WSUB,
+            32 => Op::WMUL,
+            33 => Op::WDIV,
+            34 => Op::WREM,
+            35 => Op::WNEG,
+            _ => return None,
+        };
+
+        let size = match op {
+            Op::INT(_) | Op::STR(_) | Op::LOAD(_) | Op::STORE(_) | Op::CALL(_) => 5,
+            Op::JMP(_) | Op::JZ(_) => 5,
+            _ => 1,
+        };
+        Some((op, pc + size))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FunctionCode {
+    pub name: String,
+    pub file: String,
+    pub params: u32,
+    pub locals: u32,
+    pub code: Vec<u8>,
+    pub lines: Vec<(u32, u32)>,
+}
+
+impl FunctionCode {
+    pub fn line(&self, pc: usize) -> usize {
+        self.lines
+            .iter()
+            .take_while(|(at, _)| *at as usize <= pc)
+            .last()
+            .map_or(0, |(_, line)| *line as usize)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Bytecode {
+    pub strings: Vec<Vec<u8>>,
+    pub functions: Vec<FunctionCode>,
+    pub main: u32,
+}
+
+impl Bytecode {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+
+        write_u32(&mut out, self.strings.len() as u32);
+        for s in &self.strings {
+            write_bytes(&mut out, s);
+        }
+
+        write_u32(&mut out, self.functions.len() as u32);
+        for func in &self.functions {
+            write_bytes(&mut out, func.name.as_bytes());
+            write_bytes(&mut out, func.file.as_bytes());
+            write_u32(&mut out, func.params);
+            write_u32(&mut out, func.locals);
+            write_bytes(&mut out, &func.code);
+            write_u32(&mut out, func.lines.len() as u32);
+            for (pc, line) in &func.lines {
+                write_u32(&mut out, *pc);
+                write_u32(&mut out, *line);
+            }
+        }
+
+        write_u32(&mut out, self.main);
+        out
+    }
+
+    pub fn deserialize(data: &[u8]) -> Result<Bytecode, String> {
+        let mut reader = Reader { data, pos: 0 };
+
+        if reader.take(4)? != MAGIC {
+            return Err("not an Edge bytecode file".to_string());
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(format!(
+                "unsupported bytecode version {}, expected {}",
+                version, VERSION
+            ));
+        }
+
+        let mut strings = Vec::new();
+        for _ in 0..reader.u32()? {
+            strings.push(reader.bytes()?.to_vec());
+        }
+
+        let mut functions = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = String::from_utf8(reader.bytes()?.to_vec())
+                .map_err(|_| "function name is not valid utf-8".to_string())?;
+            let file = String::from_utf8(reader.bytes()?.to_vec())
+                .map_err(|_| "file name is not valid utf-8".to_string())?;
+            let params = reader.u32()?;
+            let locals = reader.u32()?;
+            let code = reader.bytes()?.to_vec();
+            let mut lines = Vec::new();
+            for _ in 0..reader.u32()? {
+                lines.push((reader.u32()?, reader.u32()?));
+            }
+            functions.push(FunctionCode {
+                name,
+                file,
+                params,
+                locals,
+                code,
+                lines,
+            });
+        }
+
+        let main = reader.u32()?;
+        if reader.pos != data.len() {
+            return Err("trailing data after the bytecode".to_string());
+        }
+
+        let bytecode = Bytecode {
+            strings,
+            functions,
+            main,
+        };
+        bytecode.validate()?;
+        Ok(bytecode)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let main = self
+            .functions
+            .get(self.main as usize)
+            .ok_or_else(|| "main function index is out of range".to_string())?;
+        if main.params > 1 {
+            return Err("main takes more than one parameter".to_string());
+        }
+
+        let mut decoded = Vec::new();
+        for func in &self.functions {
+            if func.locals > MAX_LOCALS {
+                return Err(format!(
+                    "function '{}' has more than {} locals",
+                    func.name, MAX_LOCALS
+                ));
+            }
+            if func.params > func.locals {
+                return Err(format!(
+                    "function '{}' has more params than locals",
+                    func.name
+                ));
+            }
+
+            let mut pc = 0;
+            let mut last = None;
+            let mut code = HashMap::new();
+            let mut jumps = Vec::new();
+            while pc < func.code.len() {
+                let (op, next) = Op::decode(&func.code, pc).ok_or_else(|| {
+                    format!("invalid instruction in '{}' at offset {}", func.name, pc)
+                })?;
+
+                let in_range = match op {
+                    Op::STR(n) => (n as usize) < self.strings.len(),
+                    Op::LOAD(n) | Op::STORE(n) => n < func.locals,
+                    Op::CALL(n) => (n as usize) < self.functions.len(),
+                    Op::JMP(n) | Op::JZ(n) => {
+                        jumps.push((pc, n as usize));
+                        true
+                    }
+                    _ => true,
+                };
+                if !in_range {
+                    return Err(format!(
+                        "operand out of range in '{}' at offset {}",
+                        func.name, pc
+                    ));
+                }
+                code.insert(pc, (op, next));
+                last = Some(op);
+                pc = next;
+            }
+
+            if let Some((pc, _)) = jumps.iter().find(|(_, target)| !code.contains_key(target)) {
+                return Err(format!(
+                    "jump to an invalid offset in '{}' at offset {}",
+                    func.name, pc
+                ));
+            }
+
+            if !matches!(last, Some(Op::RET | Op::END)) {
+                return Err(format!(
+                    "function '{}' does not end with a return",
+                    func.name
+                ));
+            }
+            decoded.push(code);
+        }
+
+        let returns = self
+            .functions
+            .iter()
+            .zip(&decoded)
+            .map(|(func, code)| returns(func, code))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (func, code) in self.functions.iter().zip(&decoded) {
+            self.check_stack(func, code, &returns)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_stack(
+        &self,
+        func: &FunctionCode,
+        code: &HashMap<usize, (Op, usize)>,
+        returns: &[Option<bool>],
+    ) -> Result<(), String> {
+        let mut locals = vec![Type::INT; func.locals as usize];
+        locals[..func.params as usize].fill(Type::ANY);
+        let mut states = HashMap::from([(
+            0,
+            State {
+                stack: Vec::new(),
+                locals,
+            },
+        )]);
+        let mut work = vec![0];
+
+        while let Some(pc) = work.pop() {
+            let mut state = states[&pc].clone();
+            let (op, next) = code[&pc];
+            let successors = self
+                .step(op, next, &mut state, returns)
+                .map_err(|e| format!("{} in '{}' at offset {}", e, func.name, pc))?;
+
+            for target in successors {
+                let changed = match states.get_mut(&target) {
+                    Some(existing) => existing
+                        .merge(&state)
+                        .map_err(|e| format!("{} in '{}' at offset {}", e, func.name, target))?,
+                    None => {
+                        states.insert(target, state.clone());
+                        true
+                    }
+                };
+                if changed {
+                    work.push(target);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn step(
+        &self,
+        op: Op,
+        next: usize,
+        state: &mut State,
+        returns: &[Option<bool>],
+    ) -> Result<Vec<usize>, &'static str> {
+        match op {
+            Op::INT(_) => state.stack.push(Type::INT),
+            Op::STR(_) => state.stack.push(Type::STR),
+            Op::LOAD(n) => state.stack.push(state.locals[n as usize]),
+            Op::STORE(n) => state.locals[n as usize] = state.pop()?,
+            Op::CALL(n) => {
+                let params = self.functions[n as usize].params as usize;
+                if state.stack.len() < params {
+                    return Err("stack underflow");
+                }
+                state.stack.truncate(state.stack.len() - params);
+                match returns[n as usize] {
+                    Some(true) => state.stack.push(Type::ANY),
+                    Some(false) => {}
+                    None => return Ok(Vec::new()),
+                }
+            }
+            Op::POP => {
+                state.pop()?;
+            }
+            Op::RET | Op::END => {
+                if op == Op::RET {
+                    state.pop()?;
+                }
+                if !state.stack.is_empty() {
+                    return Err("values left on the stack at return");
+                }
+                return Ok(Vec::new());
+            }
+            Op::JMP(target) => return Ok(vec![target as usize]),
+            Op::JZ(target) => {
+                state.pop_int()?;
+                return Ok(vec![target as usize, next]);
+            }
+            Op::ADD
+            | Op::SUB
+            | Op::MUL
+            | Op::DIV
+            | Op::REM
+            | Op::EQ
+            | Op::NE
+            | Op::LT
+            | Op::LE
+            | Op::GT
+            | Op::GE
+            | Op::WADD
+            | Op::WSUB
+            | Op::WMUL
+            | Op::WDIV
+            | Op::WREM => {
+                state.pop_int()?;
+                state.pop_int()?;
+                state.stack.push(Type::INT);
+            }
+            Op::NEG | Op::WNEG | Op::PUTCHAR => {
+                state.pop_int()?;
+                state.stack.push(Type::INT);
+            }
+            Op::EXIT => state.pop_int()?,
+            Op::ARGS => state.stack.push(Type::SLICE),
+            Op::PRINT => {
+                if !matches!(state.pop()?, Type::STR | Type::ANY) {
+                    return Err("expected a string on the stack");
+                }
+            }
+            Op::STRLEN | Op::SLICELEN => {
+                state.pop_sequence()?;
+                state.stack.push(Type::INT);
+            }
+            Op::STRAT | Op::SLICEAT => {
+                state.pop_int()?;
+                let element = match state.pop_sequence()? {
+                    Type::STR => Type::INT,
+                    Type::SLICE => Type::STR,
+                    _ => Type::ANY,
+                };
+                state.stack.push(element);
+            }
+        }
+        Ok(vec![next])
+    }
+
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+
+        for (i, s) in self.strings.iter().enumerate() {
+            let _ = writeln!(out, "string {} {:?}", i, String::from_utf8_lossy(s));
+        }
+
+        for (i, func) in self.functions.iter().enumerate() {
+            let main = if i as u32 == self.main {
+                " (entry)"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                out,
+                "\nfn {} {} params={} locals={}{}",
+                i, func.name, func.params, func.locals, main
+            );
+
+            let mut pc = 0;
+            while let Some((op, next)) = Op::decode(&func.code, pc) {
+                let text = match op {
+                    Op::INT(n) => format!("INT {}", n),
+                    Op::STR(n) => format!(
+                        "STR {} ; {:?}",
+                        n,
+                        String::from_utf8_lossy(&self.strings[n as usize])
+                    ),
+                    Op::LOAD(n) => format!("LOAD {}", n),
+                    Op::STORE(n) => format!("STORE {}", n),
+                    Op::CALL(n) => format!("CALL {} ; {}", n, self.functions[n as usize].name),
+                    Op::JMP(n) => format!("JMP {:04}", n),
+                    Op::JZ(n) => format!("JZ {:04}", n),
+                    op => format!("{:?}", op),
+                };
+                let _ = writeln!(out, "  {:04}  {}", pc, text);
+                pc = next;
+            }
+        }
+
+        out
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Type {
+    INT,
+    STR,
+    SLICE,
+    ANY,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct State {
+    stack: Vec<Type>,
+    locals: Vec<Type>,
+}
+
+impl State {
+    fn pop(&mut self) -> Result<Type, &'static str> {
+        self.stack.pop().ok_or("stack underflow")
+    }
+
+    fn pop_int(&mut self) -> Result<(), &'static str> {
+        match self.pop()? {
+            Type::INT | Type::ANY => Ok(()),
+            _ => Err("expected an int on the stack"),
+        }
+    }
+
+    fn pop_sequence(&mut self) -> Result<Type, &'static str> {
+        match self.pop()? {
+            Type::INT => Err("expected a string or slice on the stack"),
+            ty => Ok(ty),
+        }
+    }
+
+    fn merge(&mut self, other: &State) -> Result<bool, &'static str> {
+        if self.stack.len() != other.stack.len() {
+            return Err("stack depth differs between paths");
+        }
+        let merge = |a: &[Type], b: &[Type]| -> Vec<Type> {
+            a.iter()
+                .zip(b)
+                .map(|(a, b)| if a == b { *a } else { Type::ANY })
+                .collect()
+        };
+        let merged = State {
+            stack: merge(&self.stack, &other.stack),
+            locals: merge(&self.locals, &other.locals),
+        };
+        let changed = merged != *self;
+        *self = merged;
+        Ok(changed)
+    }
+}
+
+fn returns(
+    func: &FunctionCode,
+    code: &HashMap<usize, (Op, usize)>,
+) -> Result<Option<bool>, String> {
+    let mut seen = HashSet::new();
+    let mut work = vec![0];
+    let mut value = None;
+
+    while let Some(pc) = work.pop() {
+        if !seen.insert(pc) {
+            continue;
+        }
+        let (op, next) = code[&pc];
+        match op {
+            Op::RET | Op::END => {
+                let returns = op == Op::RET;
+                if value.is_some_and(|v| v != returns) {
+                    return Err(format!(
+                        "function '{}' returns both with and without a value",
+                        func.name
+                    ));
+                }
+                value = Some(returns);
+            }
+            Op::JMP(target) => work.push(target as usize),
+            Op::JZ(target) => work.extend([target as usize, next]),
+            _ => work.push(next),
+        }
+    }
+
+    Ok(value)
+}
+
+fn write_u32(out: &mut Vec<u8>, n: u32) {
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_u32(out, bytes.len() as u32);
+    out.extend_from_slice(bytes);
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos.saturating_add(len))
+            .ok_or_else(|| "unexpected end of bytecode".to_string())?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::vm::Vm;
+    use crate::interpreter::interpreter::Halt;
+    use crate::{Format, Options, Output};
+
+    const SOURCE: &str = "fib :: int(n: int) {\n    if n < 2 {\n        return n;\n    }\n    return fib(n - 1) + fib(n - 2);\n}\n\nshow :: void(n: int) {\n    if n >= 10 {\n        show(n / 10);\n    }\n    putchar(48 + n % 10);\n    return;\n}\n\nmain :: int(argc: int) {\n    let argv = args();\n    let i = 0;\n    while i < 10 {\n        show(fib(i));\n        putchar(32);\n        i = i + 1;\n    }\n    print(argv[argc - 1]);\n    print(\"\\n\");\n    return len(argv[1]) + wrapping_mul(-2, -3);\n}\n";
+
+    fn compile(source: &str) -> Bytecode {
+        let options = Options {
+            format: Format::BYTECODE,
+            ..Options::new("test.ed")
+        };
+        match crate::compile(source, &options).map(|compiled| compiled.output) {
+            Ok(Output::BYTECODE(bytecode)) => bytecode,
+            result => panic!("cannot compile test program: {:?}", result),
+        }
+    }
+
+    fn run(bytecode: &Bytecode) -> (Halt, String) {
+        let args = vec!["test".to_string(), "edge".to_string()];
+        let mut out = Vec::new();
+        let halt = Vm::new(bytecode, args, &mut out).run().unwrap();
+        (halt, String::from_utf8(out).unwrap())
+    }
+
+    fn load(code: &[Op], locals: u32) -> Result<Bytecode, String> {
+        let mut encoded = Vec::new();
+        for op in code {
+            op.encode(&mut encoded);
+        }
+        let bytecode = Bytecode {
+            strings: vec![b"edge".to_vec()],
+            functions: vec![FunctionCode {
+                name: "main".to_string(),
+                file: "test.ed".to_string(),
+                params: 0,
+                locals,
+                code: encoded,
+                lines: Vec::new(),
+            }],
+            main: 0,
+        };
+        Bytecode::deserialize(&bytecode.serialize())
+    }
+
+    fn rejects(code: &[Op], locals: u32, error: &str) {
+        match load(code, locals) {
+            Ok(_) => panic!("bytecode {:?} was accepted", code),
+            Err(e) => assert!(e.contains(error), "expected '{}' but got '{}'", error, e),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytecode = compile(SOURCE);
+        let data = bytecode.serialize();
+        let loaded = Bytecode::deserialize(&data).unwrap();
+        assert_eq!(loaded.serialize(), data);
+
+        let expected = (Halt::EXIT(10), "0 1 1 2 3 5 8 13 21 34 edge\n".to_string());
+        assert_eq!(run(&bytecode), expected);
+        assert_eq!(run(&loaded), expected);
+    }
+
+    #[test]
+    fn halts() {
+        let bytecode =
+            compile("main :: int() {\n    print(\"a\");\n    exit(3);\n    return 0;\n}\n");
+        assert_eq!(run(&bytecode), (Halt::EXIT(3), "a".to_string()));
+
+        let bytecode = compile(
+            "main :: int(argc: int) {\n    print(\"b\");\n    let x = 2147483647;\n    return x + argc;\n}\n",
+        );
+        let trap = Halt::TRAP("attempt to add with overflow at test.ed:4".to_string());
+        assert_eq!(run(&bytecode), (trap, "b".to_string()));
+
+        let bytecode = compile("main :: int(argc: int) {\n    return 1 / (argc - 2);\n}\n");
+        let trap = Halt::TRAP("attempt to divide by zero".to_string());
+        assert_eq!(run(&bytecode), (trap, String::new()));
+
+        let bytecode = compile("main :: int() {\n    return args()[5][0];\n}\n");
+        let trap = Halt::TRAP("index out of bounds: the len is 2 but the index is 5".to_string());
+        assert_eq!(run(&bytecode), (trap, String::new()));
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let data = compile(SOURCE).serialize();
+        for len in 0..data.len() {
+            assert!(
+                Bytecode::deserialize(&data[..len]).is_err(),
+                "bytecode truncated to {} bytes was accepted",
+                len
+            );
+        }
+
+        let mut trailing = data.clone();
+        trailing.push(0);
+        assert!(Bytecode::deserialize(&trailing).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_jump_targets() {
+        assert!(load(&[Op::JMP(5), Op::INT(0), Op::RET], 0).is_ok());
+        rejects(
+            &[Op::JMP(1), Op::INT(0), Op::RET],
+            0,
+            "jump to an invalid offset",
+        );
+        rejects(
+            &[Op::JMP(11), Op::INT(0), Op::RET],
+            0,
+            "jump to an invalid offset",
+        );
+        rejects(
+            &[Op::INT(1), Op::JZ(u32::MAX), Op::INT(0), Op::RET],
+            0,
+            "jump to an invalid offset",
+        );
+        rejects(
+            &[Op::INT(0), Op::RET, Op::JMP(0)],
+            0,
+            "does not end with a return",
+        );
+    }
+
+    #[test]
+    fn rejects_stack_mismatches() {
+        rejects(&[Op::POP, Op::INT(0), Op::RET], 0, "stack underflow");
+        rejects(
+            &[Op::INT(1), Op::INT(0), Op::RET],
+            0,
+            "values left on the stack",
+        );
+        rejects(
+            &[Op::INT(1), Op::JZ(15), Op::INT(2), Op::INT(0), Op::RET],
+            0,
+            "stack depth differs between paths",
+        );
+        rejects(
+            &[Op::INT(7), Op::JMP(0), Op::INT(0), Op::RET],
+            0,
+            "stack depth differs between paths",
+        );
+        rejects(
+            &[Op::STR(0), Op::INT(1), Op::ADD, Op::RET],
+            0,
+            "expected an int on the stack",
+        );
+        rejects(
+            &[Op::INT(1), Op::PRINT, Op::END],
+            0,
+            "expected a string on the stack",
+        );
+        rejects(
+            &[Op::INT(0), Op::STORE(1), Op::INT(0), Op::RET],
+            1,
+            "operand out of range",
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::ast::{BinOp, Expr, Function, ItemKind, Program, Stmt, StmtKind};
+use crate::bytecode::bytecode::{Bytecode, FunctionCode, Op};
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::interpreter::includes_only;
+use crate::symbol_table::ConstType;
+use std::collections::HashMap;
+use std::mem;
+use std::path::PathBuf;
+
+pub struct Compiler {
+    functions: HashMap<String, (u32, bool)>,
+    consts: HashMap<String, i32>,
+    strings: Vec<Vec<u8>>,
+    string_ids: HashMap<Vec<u8>, u32>,
+    locals: HashMap<String, u32>,
+    code: Vec<u8>,
+    lines: Vec<(u32, u32)>,
+    line: usize,
+    overflow_checks: bool,
+    error: Option<Diagnostic>,
+    path: PathBuf,
+}
+
+impl Compiler {
+    pub fn new(overflow_checks: bool) -> Self {
+        Self {
+            functions: HashMap::new(),
+            consts: HashMap::new(),
+            strings: Vec::new(),
+            string_ids: HashMap::new(),
+            locals: HashMap::new(),
+            code: Vec::new(),
+            lines: Vec::new(),
+            line: 0,
+            overflow_checks,
+            error: None,
+            path: PathBuf::new(),
+        }
+    }
+
+    fn unsupported(&mut self, line: usize, what: &str) {
+        let path = &self.path;
+        self.error.get_or_insert_with(|| {
+            Diagnostic::new(line, format!("{} cannot be compiled to bytecode", what))
+                .with_file(path)
+        });
+    }
+
+    pub fn compile(mut self, program: &Program) -> Result<Bytecode, Box<Diagnostic>> {
+        let mut funcs = Vec::new();
+
+        for module in &program.modules {
+            self.path = module.path.clone();
+            for item in &module.items {
+                match &item.kind {
+                    ItemKind::FUNCTION(func) => {
+                        let returns = func.ret_type.value_type().is_some();
+                        self.functions
+                            .insert(func.name.clone(), (funcs.len() as u32, returns));
+                        funcs.push((func, &module.path));
+                    }
+                    ItemKind::CONST(constant) => {
+                        if let ConstType::INT32(n) = constant.value {
+                            self.consts.insert(constant.name.clone(), n);
+                        }
+                    }
+                    ItemKind::IMPORT(_) => {}
+                    ItemKind::CCODE(parts) => {
+                        if !includes_only(parts) {
+                            self.unsupported(item.line, "c code");
+                        }
+                    }
+                }
+            }
+        }
+
+        let functions = funcs
+            .into_iter()
+            .map(|(func, path)| {
+                self.path = path.clone();
+                self.function(func)
+            })
+            .collect();
+
+        match self.error {
+            Some(e) => Err(e.into()),
+            None => Ok(Bytecode {
+                strings: self.strings,
+                functions,
+                main: self.functions["main"].0,
+            }),
+        }
+    }
+
+    fn function(&mut self, func: &Function) -> FunctionCode {
+        self.locals.clear();
+        for (param, _) in &func.params {
+            self.local(param);
+        }
+
+        self.block(&func.body);
+
+        if !matches!(func.body.last().map(|s| &s.kind), Some(StmtKind::RETURN(_))) {
+            self.emit(Op::END);
+        }
+
+        FunctionCode {
+            name: func.name.clone(),
+            file: self.path.display().to_string(),
+            params: func.params.len() as u32,
+            locals: self.locals.len() as u32,
+            code: mem::take(&mut self.code),
+            lines: mem::take(&mut self.lines),
+        }
+    }
+
+    fn block(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            self.line = stmt.line;
+            match &stmt.kind {
+                StmtKind::LET(name, _, None) => {
+                    self.local(name);
+                }
+                StmtKind::LET(name, _, Some(value)) | StmtKind::ASSIGN(name, value) => {
+                    self.expr(value);
+                    let slot = self.local(name);
+                    self.emit(Op::STORE(slot));
+                }
+                StmtKind::EXPR(expr) => {
+                    if self.expr(expr) {
+                        self.emit(Op::POP);
+                    }
+                }
+                StmtKind::RETURN(Some(value)) => {
+                    self.expr(value);
+                    self.emit(Op::RET);
+                }
+                StmtKind::RETURN(None) => self.emit(Op::END),
+                StmtKind::IF(cond, then, otherwise) => {
+                    self.expr(cond);
+                    let jz = self.jump(Op::JZ(0));
+                    self.block(then);
+                    if otherwise.is_empty() {
+                        self.patch(jz);
+                    } else {
+                        let jmp = self.jump(Op::JMP(0));
+                        self.patch(jz);
+                        self.block(otherwise);
+                        self.patch(jmp);
+                    }
+                }
+                StmtKind::WHILE(cond, body) => {
+                    let start = self.code.len() as u32;
+                    self.expr(cond);
+                    let jz = self.jump(Op::JZ(0));
+                    self.block(body);
+                    self.emit(Op::JMP(start));
+                    self.patch(jz);
+                }
+                StmtKind::CCODE(_) => self.unsupported(stmt.line, "c code"),
+            }
+        }
+    }
+
+    fn jump(&mut self, op: Op) -> usize {
+        let at = self.code.len();
+        self.emit(op);
+        at
+    }
+
+    fn patch(&mut self, at: usize) {
+        let target = (self.code.len() as u32).to_le_bytes();
+        self.code[at + 1..at + 5].copy_from_slice(&target);
+    }
+
+    fn local(&mut self, name: &str) -> u32 {
+        let next = self.locals.len() as u32;
+        *self.locals.entry(name.to_string()).or_insert(next)
+    }
+
+    fn string(&mut self, s: &str) -> u32 {
+        let bytes = s.as_bytes().to_vec();
+        if let Some(id) = self.string_ids.get(&bytes) {
+            return *id;
+        }
+        let id = self.strings.len() as u32;
+        self.strings.push(bytes.clone());
+        self.string_ids.insert(bytes, id);
+        id
+    }
+
+    fn emit(&mut self, op: Op) {
+        let line = self.line as u32;
+        if self.lines.last().is_none_or(|(_, last)| *last != line) {
+            self.lines.push((self.code.len() as u32, line));
+        }
+        op.encode(&mut self.code);
+    }
+
+    fn expr(&mut self, expr: &Expr) -> bool {
+        match expr {
+            Expr::NUMBER(n) => self.emit(Op::INT(*n)),
+            Expr::STRING(s) => {
+                let id = self.string(s);
+                self.emit(Op::STR(id));
+            }
+            Expr::VAR(name) => {
+                let slot = self.locals[name];
+                self.emit(Op::LOAD(slot));
+            }
+            Expr::CONST(name) => self.emit(Op::INT(self.consts[name])),
+            Expr::CALL(name, args) => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                let (index, returns) = self.functions[name];
+                self.emit(Op::CALL(index));
+                return returns;
+            }
+            Expr::BUILTIN(name, args) => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                let op = match name.as_str() {
+                    "wrapping_add" => Op::WADD,
+                    "wrapping_sub" => Op::WSUB,
+                    "wrapping_mul" => Op::WMUL,
+                    "checked_add" => Op::ADD,
+                    "checked_sub" => Op::SUB,
+                    "checked_mul" => Op::MUL,
+                    "putchar" => Op::PUTCHAR,
+                    "exit" => Op::EXIT,
+                    "args" => Op::ARGS,
+                    "print" => Op::PRINT,
+                    "str_len" => Op::STRLEN,
+                    "slice_len" => Op::SLICELEN,
+                    "str_at" => Op::STRAT,
+                    "slice_at" => Op::SLICEAT,
+                    _ => unreachable!("unknown builtin {}", name),
+                };
+                self.emit(op);
+                return !matches!(op, Op::EXIT | Op::PRINT);
+            }
+            Expr::BINARY(op, lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+                let checks = self.overflow_checks;
+                self.emit(match op {
+                    BinOp::ADD if checks => Op::ADD,
+                    BinOp::SUB if checks => Op::SUB,
+                    BinOp::MUL if checks => Op::MUL,
+                    BinOp::DIV if checks => Op::DIV,
+                    BinOp::REM if checks => Op::REM,
+                    BinOp::ADD => Op::WADD,
+                    BinOp::SUB => Op::WSUB,
+                    BinOp::MUL => Op::WMUL,
+                    BinOp::DIV => Op::WDIV,
+                    BinOp::REM => Op::WREM,
+                    BinOp::EQ => Op::EQ,
+                    BinOp::NE => Op::NE,
+                    BinOp::LT => Op::LT,
+                    BinOp::LE => Op::LE,
+                    BinOp::GT => Op::GT,
+                    BinOp::GE => Op::GE,
+                });
+            }
+            Expr::NEG(value) => {
+                self.expr(value);
+                self.emit(if self.overflow_checks {
+                    Op::NEG
+                } else {
+                    Op::WNEG
+                });
+            }
+            Expr::IDENT(_) | Expr::INDEX(..) => {
+                unreachable!("the analyzer resolves identifiers and indexing")
+            }
+        }
+        true
+    }
+}
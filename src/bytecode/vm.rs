@@ -0,0 +1,226 @@
+use crate::ast::BinOp;
+use crate::bytecode::bytecode::{Bytecode, Op};
+use crate::interpreter::interpreter::{
+    Halt, Value, binary, c_str, checked_binary, divide_by_zero, out_of_bounds, overflow,
+};
+use std::io::Write;
+
+struct Frame {
+    func: usize,
+    pc: usize,
+    base: usize,
+}
+
+pub struct Vm<'a, W: Write> {
+    bytecode: &'a Bytecode,
+    args: Vec<Vec<u8>>,
+    stack: Vec<Value>,
+    locals: Vec<Value>,
+    frames: Vec<Frame>,
+    out: W,
+}
+
+impl<'a, W: Write> Vm<'a, W> {
+    pub fn new(bytecode: &'a Bytecode, args: Vec<String>, out: W) -> Self {
+        Self {
+            bytecode,
+            args: args.into_iter().map(String::into_bytes).collect(),
+            stack: Vec::new(),
+            locals: Vec::new(),
+            frames: Vec::new(),
+            out,
+        }
+    }
+
+    pub fn run(&mut self) -> Result<Halt, String> {
+        let halt = self.execute();
+        let _ = self.out.flush();
+        halt
+    }
+
+    fn execute(&mut self) -> Result<Halt, String> {
+        let main = self.bytecode.main as usize;
+        if self.bytecode.functions[main].params == 1 {
+            self.stack.push(Value::INT(self.args.len() as i32));
+        }
+        self.enter(main)?;
+
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let code = &self.bytecode.functions[frame.func].code;
+            let pc = frame.pc;
+            let (op, next) = Op::decode(code, pc)
+                .ok_or_else(|| format!("invalid instruction at offset {}", pc))?;
+            frame.pc = next;
+
+            match op {
+                Op::INT(n) => self.stack.push(Value::INT(n)),
+                Op::STR(n) => {
+                    let s = self.bytecode.strings[n as usize].clone();
+                    self.stack.push(Value::STR(s));
+                }
+                Op::LOAD(n) => {
+                    let value = self.locals[self.base() + n as usize].clone();
+                    self.stack.push(value);
+                }
+                Op::STORE(n) => {
+                    let slot = self.base() + n as usize;
+                    self.locals[slot] = self.pop()?;
+                }
+                Op::CALL(n) => self.enter(n as usize)?,
+                Op::POP => {
+                    self.pop()?;
+                }
+                Op::RET | Op::END => {
+                    let value = if op == Op::RET {
+                        Some(self.pop()?)
+                    } else {
+                        None
+                    };
+                    let frame = self.frames.pop().unwrap();
+                    self.locals.truncate(frame.base);
+
+                    if self.frames.is_empty() {
+                        return Ok(match value {
+                            Some(Value::INT(n)) => Halt::EXIT(n),
+                            _ => Halt::EXIT(0),
+                        });
+                    }
+                    if let Some(value) = value {
+                        self.stack.push(value);
+                    }
+                }
+                Op::JMP(target) => self.jump(target),
+                Op::JZ(target) => {
+                    if self.pop_int()? == 0 {
+                        self.jump(target);
+                    }
+                }
+                Op::ADD
+                | Op::SUB
+                | Op::MUL
+                | Op::DIV
+                | Op::REM
+                | Op::EQ
+                | Op::NE
+                | Op::LT
+                | Op::LE
+                | Op::GT
+                | Op::GE
+                | Op::WADD
+                | Op::WSUB
+                | Op::WMUL
+                | Op::WDIV
+                | Op::WREM => {
+                    let rhs = self.pop_int()?;
+                    let lhs = self.pop_int()?;
+                    let binop = op.binop().unwrap();
+                    if matches!(binop, BinOp::DIV | BinOp::REM) && rhs == 0 {
+                        return Ok(divide_by_zero());
+                    }
+                    let wrapping =
+                        matches!(op, Op::WADD | Op::WSUB | Op::WMUL | Op::WDIV | Op::WREM);
+                    let value = if wrapping {
+                        binary(binop, lhs, rhs)
+                    } else {
+                        checked_binary(binop, lhs, rhs)
+                    };
+                    match value {
+                        Some(n) => self.stack.push(Value::INT(n)),
+                        None => return Ok(self.overflow(binop.overflow_name(), pc)),
+                    }
+                }
+                Op::NEG => match self.pop_int()?.checked_neg() {
+                    Some(n) => self.stack.push(Value::INT(n)),
+                    None => return Ok(self.overflow("negate", pc)),
+                },
+                Op::WNEG => {
+                    let value = self.pop_int()?;
+                    self.stack.push(Value::INT(value.wrapping_neg()));
+                }
+                Op::PUTCHAR => {
+                    let c = self.pop_int()?;
+                    let _ = self.out.write_all(&[c as u8]);
+                    self.stack.push(Value::INT(c as u8 as i32));
+                }
+                Op::EXIT => return Ok(Halt::EXIT(self.pop_int()?)),
+                Op::ARGS => self.stack.push(Value::SLICE(self.args.clone())),
+                Op::PRINT => {
+                    if let Value::STR(s) = self.pop()? {
+                        let _ = self.out.write_all(c_str(&s));
+                    }
+                }
+                Op::STRLEN | Op::SLICELEN => {
+                    let len = match self.pop()? {
+                        Value::STR(s) => c_str(&s).len(),
+                        Value::SLICE(s) => s.len(),
+                        Value::INT(_) => return Err("len of an int".to_string()),
+                    };
+                    self.stack.push(Value::INT(len as i32));
+                }
+                Op::STRAT | Op::SLICEAT => {
+                    let index = self.pop_int()?;
+                    let base = self.pop()?;
+                    let len = match &base {
+                        Value::STR(s) => c_str(s).len(),
+                        Value::SLICE(s) => s.len(),
+                        Value::INT(_) => return Err("indexing an int".to_string()),
+                    };
+                    if index < 0 || index as usize >= len {
+                        return Ok(out_of_bounds(len, index));
+                    }
+
+                    let value = match base {
+                        Value::STR(s) => Value::INT(s[index as usize] as i32),
+                        Value::SLICE(mut s) => Value::STR(s.swap_remove(index as usize)),
+                        Value::INT(_) => unreachable!(),
+                    };
+                    self.stack.push(value);
+                }
+            }
+        }
+    }
+
+    fn enter(&mut self, func: usize) -> Result<(), String> {
+        let function = &self.bytecode.functions[func];
+        let base = self.locals.len();
+        let params = function.params as usize;
+
+        if self.stack.len() < params {
+            return Err("stack underflow".to_string());
+        }
+        self.locals
+            .extend(self.stack.drain(self.stack.len() - params..));
+        self.locals
+            .resize(base + function.locals as usize, Value::INT(0));
+
+        self.frames.push(Frame { func, pc: 0, base });
+        Ok(())
+    }
+
+    fn overflow(&self, op: &str, pc: usize) -> Halt {
+        let func = &self.bytecode.functions[self.frames.last().unwrap().func];
+        overflow(op, &func.file, func.line(pc))
+    }
+
+    fn jump(&mut self, target: u32) {
+        self.frames.last_mut().unwrap().pc = target as usize;
+    }
+
+    fn base(&self) -> usize {
+        self.frames.last().unwrap().base
+    }
+
+    fn pop(&mut self) -> Result<Value, String> {
+        self.stack
+            .pop()
+            .ok_or_else(|| "stack underflow".to_string())
+    }
+
+    fn pop_int(&mut self) -> Result<i32, String> {
+        match self.pop()? {
+            Value::INT(n) => Ok(n),
+            _ => Err("expected an int".to_string()),
+        }
+    }
+}
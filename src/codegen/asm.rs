@@ -0,0 +1,540 @@
+use crate::ast::{BinOp, Expr, Function, ItemKind, Program, Stmt, StmtKind};
+use crate::codegen::mangle;
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::interpreter::includes_only;
+use crate::symbol_table::{ConstType, VariableType};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const ARG_REGISTERS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+
+const RUNTIME: &str = r#"
+edge_exit:
+	movl $231, %eax
+	syscall
+
+edge_putchar:
+	pushq %rdi
+	movl $1, %eax
+	movl $1, %edi
+	movq %rsp, %rsi
+	movl $1, %edx
+	syscall
+	popq %rax
+	movzbl %al, %eax
+	ret
+
+edge_str_len:
+	xorl %eax, %eax
+1:
+	cmpb $0, (%rdi,%rax)
+	je 2f
+	incq %rax
+	jmp 1b
+2:
+	ret
+
+edge_print:
+	pushq %rdi
+	call edge_str_len
+	popq %rsi
+	movq %rax, %rdx
+	movl $1, %eax
+	movl $1, %edi
+	syscall
+	ret
+
+edge_str_at:
+	pushq %rdi
+	pushq %rsi
+	call edge_str_len
+	popq %rsi
+	popq %rdi
+	cmpl %eax, %esi
+	jae edge_out_of_bounds
+	movslq %esi, %rsi
+	movzbl (%rdi,%rsi), %eax
+	ret
+
+edge_write_err:
+	movl $1, %eax
+	movl $2, %edi
+	syscall
+	ret
+
+edge_write_int:
+	subq $32, %rsp
+	movl %edi, %eax
+	leaq 31(%rsp), %rsi
+	movl $10, %ecx
+	xorl %r8d, %r8d
+	testl %eax, %eax
+	jns 1f
+	movl $1, %r8d
+	negl %eax
+1:
+	xorl %edx, %edx
+	divl %ecx
+	addb $48, %dl
+	decq %rsi
+	movb %dl, (%rsi)
+	testl %eax, %eax
+	jnz 1b
+	testl %r8d, %r8d
+	jz 2f
+	decq %rsi
+	movb $45, (%rsi)
+2:
+	leaq 31(%rsp), %rdx
+	subq %rsi, %rdx
+	call edge_write_err
+	addq $32, %rsp
+	ret
+
+edge_out_of_bounds:
+	pushq %rsi
+	pushq %rax
+	leaq .Loob_len(%rip), %rsi
+	movl $(.Loob_index - .Loob_len), %edx
+	call edge_write_err
+	movl (%rsp), %edi
+	call edge_write_int
+	leaq .Loob_index(%rip), %rsi
+	movl $(.Loob_end - .Loob_index), %edx
+	call edge_write_err
+	movl 8(%rsp), %edi
+	call edge_write_int
+	leaq .Loob_end(%rip), %rsi
+	movl $1, %edx
+	call edge_write_err
+	movl $101, %edi
+	jmp edge_exit
+
+edge_overflow:
+	pushq %rdi
+	call edge_str_len
+	popq %rsi
+	movq %rax, %rdx
+	call edge_write_err
+	movl $101, %edi
+	jmp edge_exit
+
+edge_divide_by_zero:
+	leaq .Ldiv_zero(%rip), %rsi
+	movl $(.Ldiv_zero_end - .Ldiv_zero), %edx
+	call edge_write_err
+	movl $101, %edi
+	jmp edge_exit
+
+	.section .rodata
+.Loob_len:
+	.ascii "edge: index out of bounds: the len is "
+.Loob_index:
+	.ascii " but the index is "
+.Loob_end:
+	.ascii "\n"
+.Ldiv_zero:
+	.ascii "edge: attempt to divide by zero\n"
+.Ldiv_zero_end:
+"#;
+
+pub struct AsmCodegen {
+    builder: String,
+    line_info: bool,
+    overflow_checks: bool,
+    file: usize,
+    consts: HashMap<String, i32>,
+    strings: Vec<String>,
+    slots: HashMap<String, i32>,
+    depth: usize,
+    label: usize,
+    error: Option<Diagnostic>,
+    path: PathBuf,
+}
+
+impl AsmCodegen {
+    pub fn new(line_info: bool, overflow_checks: bool) -> Self {
+        Self {
+            builder: String::new(),
+            line_info,
+            overflow_checks,
+            file: 0,
+            consts: HashMap::new(),
+            strings: Vec::new(),
+            slots: HashMap::new(),
+            depth: 0,
+            label: 0,
+            error: None,
+            path: PathBuf::new(),
+        }
+    }
+
+    fn unsupported(&mut self, line: usize, what: &str) {
+        let path = &self.path;
+        self.error.get_or_insert_with(|| {
+            Diagnostic::new(
+                line,
+                format!("{} is not supported by the asm backend", what),
+            )
+            .with_file(path)
+        });
+    }
+
+    fn emit(&mut self, instruction: &str) {
+        self.builder.push('\t');
+        self.builder.push_str(instruction);
+        self.builder.push('\n');
+    }
+
+    fn new_label(&mut self) -> String {
+        self.label += 1;
+        format!(".L{}", self.label)
+    }
+
+    fn place_label(&mut self, label: &str) {
+        self.builder.push_str(&format!("{}:\n", label));
+    }
+
+    fn overflow_trap(&mut self, skip: &str, op: &str, line: usize) {
+        let message = format!(
+            "edge: attempt to {} with overflow at {}:{}\n",
+            op,
+            self.path.display(),
+            line
+        );
+        let index = match self.strings.iter().position(|s| *s == message) {
+            Some(index) => index,
+            None => {
+                self.strings.push(message);
+                self.strings.len() - 1
+            }
+        };
+
+        let label = self.new_label();
+        self.emit(&format!("{} {}", skip, label));
+        self.emit(&format!("leaq .Lstr{}(%rip), %rdi", index));
+        self.emit("jmp edge_overflow");
+        self.place_label(&label);
+    }
+
+    pub fn generate(mut self, program: &Program) -> Result<String, Box<Diagnostic>> {
+        let mut main = None;
+
+        for module in &program.modules {
+            for item in &module.items {
+                if let ItemKind::CONST(constant) = &item.kind
+                    && let ConstType::INT32(n) = constant.value
+                {
+                    self.consts.insert(constant.name.clone(), n);
+                }
+            }
+        }
+
+        self.emit(".text");
+        for (i, module) in program.modules.iter().enumerate() {
+            self.path = module.path.clone();
+            self.file = i + 1;
+            if self.line_info {
+                let path = module.path.display().to_string();
+                let str = format!(".file {} \"{}\"", self.file, path.replace('"', "\\\""));
+                self.emit(&str);
+            }
+
+            for item in &module.items {
+                match &item.kind {
+                    ItemKind::FUNCTION(func) => {
+                        if func.name == "main" {
+                            main = Some(func);
+                        }
+                        self.function(func, item.line);
+                    }
+                    ItemKind::CONST(_) | ItemKind::IMPORT(_) => {}
+                    ItemKind::CCODE(parts) => {
+                        if !includes_only(parts) {
+                            self.unsupported(item.line, "c code");
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(main) = main {
+            self.start(main);
+        }
+        self.builder.push_str(RUNTIME);
+
+        for (i, s) in self.strings.iter().enumerate() {
+            let str = format!(".Lstr{}:\n\t.asciz {}\n", i, mangle::c_string(s));
+            self.builder.push_str(&str);
+        }
+        self.builder
+            .push_str("\t.section .note.GNU-stack,\"\",@progbits\n");
+
+        match self.error {
+            Some(e) => Err(e.into()),
+            None => Ok(self.builder),
+        }
+    }
+
+    fn start(&mut self, main: &Function) {
+        self.builder.push_str("\n\t.globl _start\n_start:\n");
+        if !main.params.is_empty() {
+            self.emit("movl (%rsp), %edi");
+        }
+        self.emit("call edge__main");
+        self.emit("movl %eax, %edi");
+        self.emit("jmp edge_exit");
+    }
+
+    fn function(&mut self, func: &Function, line: usize) {
+        let name = mangle::item_name(&func.name);
+        self.slots.clear();
+        self.depth = 0;
+
+        for (i, (param, param_type)) in func.params.iter().enumerate() {
+            if let VariableType::SLICE(_) = param_type {
+                self.unsupported(line, "A slice parameter");
+            }
+            let slot = if i < ARG_REGISTERS.len() {
+                -8 * (self.slots.len() as i32 + 1)
+            } else {
+                16 + 8 * (i - ARG_REGISTERS.len()) as i32
+            };
+            self.slots.insert(param.clone(), slot);
+        }
+        let mut locals = self.slots.values().filter(|slot| **slot < 0).count() as i32;
+        self.collect_locals(&func.body, &mut locals);
+        let frame = (8 * locals + 15) / 16 * 16;
+
+        let str = format!("\n\t.globl {0}\n{0}:\n", name);
+        self.builder.push_str(&str);
+        self.emit("pushq %rbp");
+        self.emit("movq %rsp, %rbp");
+        if frame > 0 {
+            self.emit(&format!("subq ${}, %rsp", frame));
+        }
+        for (i, (param, _)) in func.params.iter().take(ARG_REGISTERS.len()).enumerate() {
+            let str = format!("movq {}, {}(%rbp)", ARG_REGISTERS[i], self.slots[param]);
+            self.emit(&str);
+        }
+
+        self.block(&func.body);
+
+        if !matches!(func.body.last().map(|s| &s.kind), Some(StmtKind::RETURN(_))) {
+            self.emit("leave");
+            self.emit("ret");
+        }
+    }
+
+    fn collect_locals(&mut self, body: &[Stmt], locals: &mut i32) {
+        for stmt in body {
+            match &stmt.kind {
+                StmtKind::LET(var, _, _) if !self.slots.contains_key(var) => {
+                    *locals += 1;
+                    self.slots.insert(var.clone(), -8 * *locals);
+                }
+                StmtKind::IF(_, then, otherwise) => {
+                    self.collect_locals(then, locals);
+                    self.collect_locals(otherwise, locals);
+                }
+                StmtKind::WHILE(_, body) => self.collect_locals(body, locals),
+                _ => {}
+            }
+        }
+    }
+
+    fn block(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            if self.line_info {
+                self.emit(&format!(".loc {} {}", self.file, stmt.line));
+            }
+
+            match &stmt.kind {
+                StmtKind::LET(_, _, None) => {}
+                StmtKind::LET(var, _, Some(value)) | StmtKind::ASSIGN(var, value) => {
+                    self.expr(value, stmt.line);
+                    let str = format!("movq %rax, {}(%rbp)", self.slots[var]);
+                    self.emit(&str);
+                }
+                StmtKind::EXPR(expr) => self.expr(expr, stmt.line),
+                StmtKind::RETURN(value) => {
+                    if let Some(value) = value {
+                        self.expr(value, stmt.line);
+                    }
+                    self.emit("leave");
+                    self.emit("ret");
+                }
+                StmtKind::IF(cond, then, otherwise) => {
+                    let (other, end) = (self.new_label(), self.new_label());
+                    self.expr(cond, stmt.line);
+                    self.emit("testl %eax, %eax");
+                    self.emit(&format!("jz {}", other));
+                    self.block(then);
+                    if !otherwise.is_empty() {
+                        self.emit(&format!("jmp {}", end));
+                    }
+                    self.place_label(&other);
+                    if !otherwise.is_empty() {
+                        self.block(otherwise);
+                        self.place_label(&end);
+                    }
+                }
+                StmtKind::WHILE(cond, body) => {
+                    let (start, end) = (self.new_label(), self.new_label());
+                    self.place_label(&start);
+                    self.expr(cond, stmt.line);
+                    self.emit("testl %eax, %eax");
+                    self.emit(&format!("jz {}", end));
+                    self.block(body);
+                    self.emit(&format!("jmp {}", start));
+                    self.place_label(&end);
+                }
+                StmtKind::CCODE(_) => self.unsupported(stmt.line, "c code"),
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, line: usize) {
+        match expr {
+            Expr::NUMBER(n) => self.emit(&format!("movl ${}, %eax", n)),
+            Expr::CONST(name) => {
+                let str = format!("movl ${}, %eax", self.consts[name]);
+                self.emit(&str);
+            }
+            Expr::STRING(s) => {
+                let str = format!("leaq .Lstr{}(%rip), %rax", self.strings.len());
+                self.strings.push(s.clone());
+                self.emit(&str);
+            }
+            Expr::VAR(name) => {
+                let str = format!("movq {}(%rbp), %rax", self.slots[name]);
+                self.emit(&str);
+            }
+            Expr::CALL(name, args) => self.call(&mangle::item_name(name), args, line),
+            Expr::BUILTIN(name, args) => match name.as_str() {
+                "putchar" | "exit" | "print" | "str_len" | "str_at" => {
+                    self.call(&format!("edge_{}", name), args, line)
+                }
+                "wrapping_add" | "wrapping_sub" | "wrapping_mul" => self.binary(
+                    BinOp::intrinsic(name).unwrap(),
+                    &args[0],
+                    &args[1],
+                    line,
+                    false,
+                ),
+                "checked_add" | "checked_sub" | "checked_mul" => self.binary(
+                    BinOp::intrinsic(name).unwrap(),
+                    &args[0],
+                    &args[1],
+                    line,
+                    true,
+                ),
+                "args" => self.unsupported(line, "args()"),
+                _ => self.unsupported(line, "Indexing or taking the len of a slice"),
+            },
+            Expr::BINARY(op, lhs, rhs) => self.binary(*op, lhs, rhs, line, self.overflow_checks),
+            Expr::NEG(value) => {
+                self.expr(value, line);
+                self.emit("negl %eax");
+                if self.overflow_checks {
+                    self.overflow_trap("jno", "negate", line);
+                }
+            }
+            Expr::IDENT(_) | Expr::INDEX(..) => {
+                unreachable!("the analyzer resolves identifiers and indexing")
+            }
+        }
+    }
+
+    fn binary(&mut self, op: BinOp, lhs: &Expr, rhs: &Expr, line: usize, checked: bool) {
+        self.expr(lhs, line);
+        self.emit("pushq %rax");
+        self.depth += 1;
+        self.expr(rhs, line);
+        self.emit("movl %eax, %ecx");
+        self.emit("popq %rax");
+        self.depth -= 1;
+
+        match op {
+            BinOp::ADD | BinOp::SUB | BinOp::MUL => {
+                self.emit(match op {
+                    BinOp::ADD => "addl %ecx, %eax",
+                    BinOp::SUB => "subl %ecx, %eax",
+                    _ => "imull %ecx, %eax",
+                });
+                if checked {
+                    self.overflow_trap("jno", op.overflow_name(), line);
+                }
+            }
+            BinOp::DIV | BinOp::REM => {
+                self.emit("testl %ecx, %ecx");
+                self.emit("jz edge_divide_by_zero");
+                self.emit("cmpl $-1, %ecx");
+                self.emit("jne 1f");
+                if op == BinOp::REM {
+                    if checked {
+                        self.emit("cmpl $-2147483648, %eax");
+                        self.overflow_trap("jne", op.overflow_name(), line);
+                    }
+                    self.emit("xorl %eax, %eax");
+                } else {
+                    self.emit("negl %eax");
+                    if checked {
+                        self.overflow_trap("jno", op.overflow_name(), line);
+                    }
+                }
+                self.emit("jmp 2f");
+                self.builder.push_str("1:\n");
+                self.emit("cltd");
+                self.emit("idivl %ecx");
+                if op == BinOp::REM {
+                    self.emit("movl %edx, %eax");
+                }
+                self.builder.push_str("2:\n");
+            }
+            BinOp::EQ | BinOp::NE | BinOp::LT | BinOp::LE | BinOp::GT | BinOp::GE => {
+                let set = match op {
+                    BinOp::EQ => "sete",
+                    BinOp::NE => "setne",
+                    BinOp::LT => "setl",
+                    BinOp::LE => "setle",
+                    BinOp::GT => "setg",
+                    _ => "setge",
+                };
+                self.emit("cmpl %ecx, %eax");
+                self.emit(&format!("{} %al", set));
+                self.emit("movzbl %al, %eax");
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], line: usize) {
+        let registers = args.len().min(ARG_REGISTERS.len());
+        let stack_args = args.len() - registers;
+        let pad = (self.depth + stack_args) % 2;
+        let reserved = args.len() + pad;
+
+        if reserved > 0 {
+            self.emit(&format!("subq ${}, %rsp", 8 * reserved));
+            self.depth += reserved;
+        }
+        for (i, arg) in args.iter().enumerate() {
+            self.expr(arg, line);
+            self.emit(&format!("movq %rax, {}(%rsp)", 8 * i));
+        }
+        for (i, register) in ARG_REGISTERS.iter().take(registers).enumerate() {
+            self.emit(&format!("movq {}(%rsp), {}", 8 * i, register));
+        }
+        if registers > 0 {
+            self.emit(&format!("addq ${}, %rsp", 8 * registers));
+            self.depth -= registers;
+        }
+
+        self.emit(&format!("call {}", name));
+
+        if stack_args + pad > 0 {
+            self.emit(&format!("addq ${}, %rsp", 8 * (stack_args + pad)));
+            self.depth -= stack_args + pad;
+        }
+    }
+}
@@ -1,48 +1,515 @@
-use crate::symbol_table::{FunctionType, VariableType};
-use std::fs;
+use crate::ast::{BinOp, CPart, Expr, Function, ItemKind, Program, Stmt, StmtKind};
+use crate::codegen::mangle;
+use crate::diagnostic::Diagnostic;
+use crate::symbol_table::{ConstType, FunctionType, VariableType};
+use std::mem;
+
+const RUNTIME: &str = r#"#include <limits.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef struct {
+	int len;
+	char **data;
+} edge_slice;
+
+static int edge_argc;
+static char **edge_argv;
+
+static inline void edge_out_of_bounds(int index, int len) {
+	fflush(stdout);
+	fprintf(stderr, "edge: index out of bounds: the len is %d but the index is %d\n", len, index);
+	exit(101);
+}
+
+static inline void edge_divide_by_zero(void) {
+	fflush(stdout);
+	fprintf(stderr, "edge: attempt to divide by zero\n");
+	exit(101);
+}
+
+static inline void edge_overflow(const char *op, const char *file, int line) {
+	fflush(stdout);
+	fprintf(stderr, "edge: attempt to %s with overflow at %s:%d\n", op, file, line);
+	exit(101);
+}
+
+static inline int edge_wrapping_add(int a, int b) {
+	return (int)((unsigned)a + (unsigned)b);
+}
+
+static inline int edge_wrapping_sub(int a, int b) {
+	return (int)((unsigned)a - (unsigned)b);
+}
+
+static inline int edge_wrapping_mul(int a, int b) {
+	return (int)((unsigned)a * (unsigned)b);
+}
+
+static inline int edge_wrapping_neg(int a) {
+	return (int)(0u - (unsigned)a);
+}
+
+static inline int edge_div(int a, int b) {
+	if (b == 0) {
+		edge_divide_by_zero();
+	}
+	if (b == -1) {
+		return edge_wrapping_neg(a);
+	}
+	return a / b;
+}
+
+static inline int edge_rem(int a, int b) {
+	if (b == 0) {
+		edge_divide_by_zero();
+	}
+	if (b == -1) {
+		return 0;
+	}
+	return a % b;
+}
+
+static inline int edge_checked_add(int a, int b, const char *file, int line) {
+	int r;
+	if (__builtin_add_overflow(a, b, &r)) {
+		edge_overflow("add", file, line);
+	}
+	return r;
+}
+
+static inline int edge_checked_sub(int a, int b, const char *file, int line) {
+	int r;
+	if (__builtin_sub_overflow(a, b, &r)) {
+		edge_overflow("subtract", file, line);
+	}
+	return r;
+}
+
+static inline int edge_checked_mul(int a, int b, const char *file, int line) {
+	int r;
+	if (__builtin_mul_overflow(a, b, &r)) {
+		edge_overflow("multiply", file, line);
+	}
+	return r;
+}
+
+static inline int edge_checked_neg(int a, const char *file, int line) {
+	if (a == INT_MIN) {
+		edge_overflow("negate", file, line);
+	}
+	return -a;
+}
+
+static inline int edge_checked_div(int a, int b, const char *file, int line) {
+	if (b == 0) {
+		edge_divide_by_zero();
+	}
+	if (a == INT_MIN && b == -1) {
+		edge_overflow("divide", file, line);
+	}
+	return a / b;
+}
+
+static inline int edge_checked_rem(int a, int b, const char *file, int line) {
+	if (b == 0) {
+		edge_divide_by_zero();
+	}
+	if (a == INT_MIN && b == -1) {
+		edge_overflow("calculate the remainder", file, line);
+	}
+	return a % b;
+}
+
+static inline edge_slice edge_args(void) {
+	edge_slice args = { edge_argc, edge_argv };
+	return args;
+}
+
+static inline void edge_print(const char *s) {
+	fputs(s, stdout);
+}
+
+static inline int edge_str_len(const char *s) {
+	return (int)strlen(s);
+}
+
+static inline int edge_str_at(const char *s, int index) {
+	int len = (int)strlen(s);
+	if (index < 0 || index >= len) {
+		edge_out_of_bounds(index, len);
+	}
+	return (unsigned char)s[index];
+}
+
+static inline const char *edge_slice_at(edge_slice s, int index) {
+	if (index < 0 || index >= s.len) {
+		edge_out_of_bounds(index, s.len);
+	}
+	return s.data[index];
+}
+"#;
 
 pub struct Codegen {
     file_name: String,
     builder: String,
+    line_info: bool,
+    overflow_checks: bool,
+    source: String,
+    line: usize,
+    depth: usize,
 }
 
 impl Codegen {
-    pub fn new(file_name: String) -> Self {
+    pub fn new(file_name: String, line_info: bool, overflow_checks: bool) -> Self {
         Self {
-            file_name: file_name,
+            file_name,
             builder: String::new(),
+            line_info,
+            overflow_checks,
+            source: String::new(),
+            line: 0,
+            depth: 0,
         }
     }
 
-    pub fn end(&self) {
-        fs::write(&self.file_name, &self.builder).unwrap();
+    pub fn c_name(name: &str) -> String {
+        mangle::c_name(name)
+    }
+
+    fn c_type(var_type: &VariableType) -> &'static str {
+        match var_type {
+            VariableType::INT32 => "int",
+            VariableType::STR => "const char *",
+            VariableType::SLICE(_) => "edge_slice",
+        }
+    }
+
+    pub fn generate(mut self, program: &Program) -> Result<String, Box<Diagnostic>> {
+        let mut declared = false;
+        let mut main = None;
+
+        self.builder.push_str(RUNTIME);
+
+        for module in &program.modules {
+            self.source = module.path.display().to_string();
+
+            for item in &module.items {
+                let plain_c = match &item.kind {
+                    ItemKind::CCODE(parts) => parts.iter().all(|p| matches!(p, CPart::TEXT(_))),
+                    ItemKind::IMPORT(_) => continue,
+                    _ => false,
+                };
+                if !declared && !plain_c {
+                    self.declarations(program);
+                    declared = true;
+                }
+
+                self.line_directive(item.line);
+                match &item.kind {
+                    ItemKind::FUNCTION(func) => {
+                        if func.name == "main" {
+                            main = Some(func);
+                        }
+                        self.function(func)
+                    }
+                    ItemKind::CONST(_) | ItemKind::IMPORT(_) => {}
+                    ItemKind::CCODE(parts) => {
+                        let code = self.c_code(parts);
+                        self.c_comp_append(code);
+                    }
+                }
+            }
+        }
+
+        if !declared {
+            self.declarations(program);
+        }
+
+        if let Some(main) = main {
+            self.main_wrapper(main);
+        }
+
+        Ok(self.builder)
     }
 
-    pub fn let_stmt(&mut self, var_name: &str, var_type: &VariableType) {
-        let (r#type, value) = match var_type {
-            VariableType::INT32(n) => ("int", n),
+    fn main_wrapper(&mut self, main: &Function) {
+        self.reset_line();
+        let args = if main.params.is_empty() { "" } else { "argc" };
+        let call = format!("edge__main({})", args);
+        let call = match main.ret_type {
+            FunctionType::INT => format!("return {};", call),
+            _ => format!("{};\n\treturn 0;", call),
         };
 
-        let str = format!("\t{} {} = {};\n", r#type, var_name, value);
+        let str = format!(
+            "int main(int argc, char **argv) {{\n\tedge_argc = argc;\n\tedge_argv = argv;\n\t{}\n}}\n",
+            call
+        );
         self.builder.push_str(&str);
     }
 
-    pub fn var_reassign(&mut self, var_name: &str, var_type: &VariableType) {
-        let (_, value) = match var_type {
-            VariableType::INT32(n) => ("", n),
+    fn declarations(&mut self, program: &Program) {
+        self.reset_line();
+        for module in &program.modules {
+            for item in &module.items {
+                if let ItemKind::CONST(constant) = &item.kind {
+                    self.const_decl(&mangle::item_name(&constant.name), &constant.value);
+                }
+            }
+        }
+
+        for module in &program.modules {
+            for item in &module.items {
+                if let ItemKind::FUNCTION(func) = &item.kind {
+                    let params = Self::c_params(&func.params);
+                    self.function_prototype(
+                        &mangle::item_name(&func.name),
+                        &func.ret_type,
+                        &params,
+                    );
+                }
+            }
+        }
+    }
+
+    fn function(&mut self, func: &Function) {
+        let params = Self::c_params(&func.params);
+        self.start_function(&mangle::item_name(&func.name), &func.ret_type, &params);
+        self.block(&func.body);
+        self.end_function();
+    }
+
+    fn indent(&self) -> String {
+        "\t".repeat(self.depth)
+    }
+
+    fn block(&mut self, body: &[Stmt]) {
+        self.depth += 1;
+
+        for stmt in body {
+            self.line = stmt.line;
+            self.line_directive(stmt.line);
+            match &stmt.kind {
+                StmtKind::LET(name, var_type, value) => {
+                    let var_type = var_type.as_ref().unwrap_or(&VariableType::INT32);
+                    let value = value.as_ref().map(|value| self.expr(value));
+                    self.let_stmt(name, var_type, value);
+                }
+                StmtKind::ASSIGN(name, value) => {
+                    let value = self.expr(value);
+                    self.var_reassign(name, value);
+                }
+                StmtKind::EXPR(Expr::CALL(name, args)) => {
+                    let args: Vec<String> = args.iter().map(|a| self.expr(a)).collect();
+                    self.function_call(mangle::item_name(name), args.join(", "));
+                }
+                StmtKind::EXPR(expr) => {
+                    let str = format!("{}{};\n", self.indent(), self.expr(expr));
+                    self.builder.push_str(&str);
+                }
+                StmtKind::IF(cond, then, otherwise) => {
+                    let str = format!("{}if ({}) {{\n", self.indent(), self.expr(cond));
+                    self.builder.push_str(&str);
+                    self.block(then);
+                    if !otherwise.is_empty() {
+                        let str = format!("{}}} else {{\n", self.indent());
+                        self.builder.push_str(&str);
+                        self.block(otherwise);
+                    }
+                    let str = format!("{}}}\n", self.indent());
+                    self.builder.push_str(&str);
+                }
+                StmtKind::WHILE(cond, body) => {
+                    let str = format!("{}while ({}) {{\n", self.indent(), self.expr(cond));
+                    self.builder.push_str(&str);
+                    self.block(body);
+                    let str = format!("{}}}\n", self.indent());
+                    self.builder.push_str(&str);
+                }
+                StmtKind::RETURN(value) => {
+                    let value = value.as_ref().map(|v| self.expr(v)).unwrap_or_default();
+                    self.return_stmt(value);
+                }
+                StmtKind::CCODE(parts) => {
+                    let code = self.c_code(parts);
+                    self.c_comp_append(code);
+                }
+            }
+        }
+
+        self.depth -= 1;
+    }
+
+    fn c_params(params: &[(String, VariableType)]) -> String {
+        if params.is_empty() {
+            return "void".to_string();
+        }
+
+        let params: Vec<String> = params
+            .iter()
+            .map(|(name, var_type)| format!("{} {}", Self::c_type(var_type), Self::c_name(name)))
+            .collect();
+        params.join(", ")
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::NUMBER(n) => n.to_string(),
+            Expr::STRING(s) => mangle::c_string(s),
+            Expr::IDENT(name) | Expr::VAR(name) => Self::c_name(name),
+            Expr::CONST(name) => mangle::item_name(name),
+            Expr::CALL(name, args) => {
+                let args: Vec<String> = args.iter().map(|a| self.expr(a)).collect();
+                format!("{}({})", mangle::item_name(name), args.join(", "))
+            }
+            Expr::BUILTIN(name, args) => {
+                let args: Vec<String> = args.iter().map(|a| self.expr(a)).collect();
+                match name.as_str() {
+                    "putchar" | "exit" => format!("{}({})", name, args.join(", ")),
+                    "slice_len" => format!("{}.len", args[0]),
+                    "checked_add" | "checked_sub" | "checked_mul" => {
+                        format!("edge_{}({}, {})", name, args.join(", "), self.location())
+                    }
+                    _ => format!("edge_{}({})", name, args.join(", ")),
+                }
+            }
+            Expr::INDEX(base, index) => format!("{}[{}]", self.expr(base), self.expr(index)),
+            Expr::BINARY(op, lhs, rhs) => {
+                let (lhs, rhs) = (self.expr(lhs), self.expr(rhs));
+                if self.overflow_checks {
+                    let name = match op {
+                        BinOp::ADD => Some("add"),
+                        BinOp::SUB => Some("sub"),
+                        BinOp::MUL => Some("mul"),
+                        BinOp::DIV => Some("div"),
+                        BinOp::REM => Some("rem"),
+                        _ => None,
+                    };
+                    if let Some(name) = name {
+                        return format!(
+                            "edge_checked_{}({}, {}, {})",
+                            name,
+                            lhs,
+                            rhs,
+                            self.location()
+                        );
+                    }
+                }
+                match op {
+                    BinOp::ADD => format!("edge_wrapping_add({}, {})", lhs, rhs),
+                    BinOp::SUB => format!("edge_wrapping_sub({}, {})", lhs, rhs),
+                    BinOp::MUL => format!("edge_wrapping_mul({}, {})", lhs, rhs),
+                    BinOp::DIV => format!("edge_div({}, {})", lhs, rhs),
+                    BinOp::REM => format!("edge_rem({}, {})", lhs, rhs),
+                    BinOp::EQ => format!("({} == {})", lhs, rhs),
+                    BinOp::NE => format!("({} != {})", lhs, rhs),
+                    BinOp::LT => format!("({} < {})", lhs, rhs),
+                    BinOp::LE => format!("({} <= {})", lhs, rhs),
+                    BinOp::GT => format!("({} > {})", lhs, rhs),
+                    BinOp::GE => format!("({} >= {})", lhs, rhs),
+                }
+            }
+            Expr::NEG(value) if self.overflow_checks => {
+                format!(
+                    "edge_checked_neg({}, {})",
+                    self.expr(value),
+                    self.location()
+                )
+            }
+            Expr::NEG(value) => format!("edge_wrapping_neg({})", self.expr(value)),
+        }
+    }
+
+    fn c_source(&self) -> String {
+        self.source.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
+    fn location(&self) -> String {
+        format!("\"{}\", {}", self.c_source(), self.line)
+    }
+
+    fn c_code(&self, parts: &[CPart]) -> String {
+        parts
+            .iter()
+            .map(|part| match part {
+                CPart::TEXT(text) => text.clone(),
+                CPart::IDENT(name) => Self::c_name(name),
+                CPart::CONST(name) | CPart::ITEM(name) => mangle::item_name(name),
+            })
+            .collect()
+    }
+
+    fn line_directive(&mut self, line: usize) {
+        if self.line_info {
+            let str = format!("#line {} \"{}\"\n", line, self.c_source());
+            self.builder.push_str(&str);
+        }
+    }
+
+    fn reset_line(&mut self) {
+        if self.line_info {
+            let source = mem::replace(&mut self.source, self.file_name.clone());
+            let line = self.builder.matches('\n').count() + 2;
+            self.line_directive(line);
+            self.source = source;
+        }
+    }
+
+    pub fn const_decl(&mut self, const_name: &str, const_type: &ConstType) {
+        match const_type {
+            ConstType::INT32(n) => {
+                let str = format!("static const int {} = {};\n", const_name, n);
+                self.builder.push_str(&str);
+            }
+            ConstType::STRING(_) => {}
+        }
+    }
+
+    pub fn let_stmt(&mut self, var_name: &str, var_type: &VariableType, value: Option<String>) {
+        let str = match value {
+            Some(value) => format!(
+                "{}{} {} = {};\n",
+                self.indent(),
+                Self::c_type(var_type),
+                Self::c_name(var_name),
+                value
+            ),
+            None => format!(
+                "{}{} {};\n",
+                self.indent(),
+                Self::c_type(var_type),
+                Self::c_name(var_name)
+            ),
         };
+        self.builder.push_str(&str);
+    }
 
-        let str = format!("\t{} = {};\n", var_name, value);
+    pub fn var_reassign(&mut self, var_name: &str, value: String) {
+        let str = format!("{}{} = {};\n", self.indent(), Self::c_name(var_name), value);
         self.builder.push_str(&str);
     }
 
-    pub fn start_function(&mut self, func_name: &str, func_type: &FunctionType) {
+    pub fn function_prototype(&mut self, func_name: &str, func_type: &FunctionType, params: &str) {
+        let r#type = match func_type {
+            FunctionType::VOID => "void",
+            FunctionType::INT => "int",
+            FunctionType::STR => "const char *",
+        };
+
+        let str = format!("{} {}({});\n", r#type, func_name, params);
+        self.builder.push_str(&str)
+    }
+
+    pub fn start_function(&mut self, func_name: &str, func_type: &FunctionType, params: &str) {
         let r#type = match func_type {
             FunctionType::VOID => "void",
             FunctionType::INT => "int",
+            FunctionType::STR => "const char *",
         };
 
-        let str = format!("{} {}() {{\n", r#type, func_name);
+        let str = format!("{} {}({}) {{\n", r#type, func_name, params);
         self.builder.push_str(&str)
     }
 
@@ -51,17 +518,17 @@ impl Codegen {
     }
 
     pub fn function_call(&mut self, func_name: String, func_val: String) {
-        let str = format!("\t{}({});\n", func_name, func_val);
+        let str = format!("{}{}({});\n", self.indent(), func_name, func_val);
         self.builder.push_str(&str);
     }
 
     pub fn return_stmt(&mut self, value: String) {
-        let str = format!("\treturn {};\n", value);
+        let str = format!("{}return {};\n", self.indent(), value);
         self.builder.push_str(&str);
     }
 
     pub fn c_comp_append(&mut self, value: String) {
         self.builder.push_str(&value);
-        self.builder.push_str("\n");
+        self.builder.push('\n');
     }
 }
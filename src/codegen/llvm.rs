@@ -0,0 +1,637 @@
+use crate::ast::{BinOp, Expr, Function, ItemKind, Program, Stmt, StmtKind};
+use crate::codegen::mangle;
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::interpreter::includes_only;
+use crate::symbol_table::{ConstType, FunctionType, VariableType};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const RUNTIME: &str = r#"%edge_slice = type { i32, ptr }
+
+@edge_args_slice = global %edge_slice zeroinitializer
+@edge_oob_fmt = private unnamed_addr constant [62 x i8] c"edge: index out of bounds: the len is %d but the index is %d\0A\00"
+@edge_div_zero_msg = private unnamed_addr constant [33 x i8] c"edge: attempt to divide by zero\0A\00"
+@stdout = external global ptr
+@stderr = external global ptr
+
+declare i32 @putchar(i32)
+declare void @exit(i32) noreturn
+declare i64 @strlen(ptr)
+declare i32 @fflush(ptr)
+declare i32 @fputs(ptr, ptr)
+declare i32 @fprintf(ptr, ptr, ...)
+declare { i32, i1 } @llvm.sadd.with.overflow.i32(i32, i32)
+declare { i32, i1 } @llvm.ssub.with.overflow.i32(i32, i32)
+declare { i32, i1 } @llvm.smul.with.overflow.i32(i32, i32)
+
+define internal void @edge_out_of_bounds(i32 %index, i32 %len) noreturn {
+	call i32 @fflush(ptr null)
+	%err = load ptr, ptr @stderr
+	call i32 (ptr, ptr, ...) @fprintf(ptr %err, ptr @edge_oob_fmt, i32 %len, i32 %index)
+	call void @exit(i32 101)
+	unreachable
+}
+
+define internal void @edge_divide_by_zero() noreturn {
+	call i32 @fflush(ptr null)
+	%err = load ptr, ptr @stderr
+	call i32 @fputs(ptr @edge_div_zero_msg, ptr %err)
+	call void @exit(i32 101)
+	unreachable
+}
+
+define internal void @edge_overflow(ptr %msg) noreturn {
+	call i32 @fflush(ptr null)
+	%err = load ptr, ptr @stderr
+	call i32 @fputs(ptr %msg, ptr %err)
+	call void @exit(i32 101)
+	unreachable
+}
+
+define internal void @edge_print(ptr %s) {
+	%out = load ptr, ptr @stdout
+	call i32 @fputs(ptr %s, ptr %out)
+	ret void
+}
+
+define internal i32 @edge_str_len(ptr %s) {
+	%len = call i64 @strlen(ptr %s)
+	%r = trunc i64 %len to i32
+	ret i32 %r
+}
+
+define internal i32 @edge_div(i32 %a, i32 %b) {
+	%zero = icmp eq i32 %b, 0
+	br i1 %zero, label %divide_by_zero, label %nonzero
+divide_by_zero:
+	call void @edge_divide_by_zero()
+	unreachable
+nonzero:
+	%minus = icmp eq i32 %b, -1
+	br i1 %minus, label %negate, label %divide
+negate:
+	%n = sub i32 0, %a
+	ret i32 %n
+divide:
+	%r = sdiv i32 %a, %b
+	ret i32 %r
+}
+
+define internal i32 @edge_rem(i32 %a, i32 %b) {
+	%zero = icmp eq i32 %b, 0
+	br i1 %zero, label %divide_by_zero, label %nonzero
+divide_by_zero:
+	call void @edge_divide_by_zero()
+	unreachable
+nonzero:
+	%minus = icmp eq i32 %b, -1
+	br i1 %minus, label %negate, label %divide
+negate:
+	ret i32 0
+divide:
+	%r = srem i32 %a, %b
+	ret i32 %r
+}
+
+define internal i32 @edge_str_at(ptr %s, i32 %index) {
+	%len = call i32 @edge_str_len(ptr %s)
+	%ok = icmp ult i32 %index, %len
+	br i1 %ok, label %in_bounds, label %out_of_bounds
+out_of_bounds:
+	call void @edge_out_of_bounds(i32 %index, i32 %len)
+	unreachable
+in_bounds:
+	%i = sext i32 %index to i64
+	%p = getelementptr i8, ptr %s, i64 %i
+	%c = load i8, ptr %p
+	%r = zext i8 %c to i32
+	ret i32 %r
+}
+
+define internal ptr @edge_slice_at(ptr %s, i32 %index) {
+	%len = load i32, ptr %s
+	%ok = icmp ult i32 %index, %len
+	br i1 %ok, label %in_bounds, label %out_of_bounds
+out_of_bounds:
+	call void @edge_out_of_bounds(i32 %index, i32 %len)
+	unreachable
+in_bounds:
+	%dp = getelementptr %edge_slice, ptr %s, i32 0, i32 1
+	%data = load ptr, ptr %dp
+	%i = sext i32 %index to i64
+	%p = getelementptr ptr, ptr %data, i64 %i
+	%r = load ptr, ptr %p
+	ret ptr %r
+}
+"#;
+
+pub struct LlvmCodegen {
+    source: String,
+    builder: String,
+    overflow_checks: bool,
+    consts: HashMap<String, i32>,
+    functions: HashMap<String, FunctionType>,
+    strings: Vec<Vec<u8>>,
+    vars: HashMap<String, VariableType>,
+    temp: usize,
+    label: usize,
+    line: usize,
+    terminated: bool,
+    error: Option<Diagnostic>,
+    path: PathBuf,
+}
+
+impl LlvmCodegen {
+    pub fn new(source: String, overflow_checks: bool) -> Self {
+        Self {
+            source,
+            builder: String::new(),
+            overflow_checks,
+            consts: HashMap::new(),
+            functions: HashMap::new(),
+            strings: Vec::new(),
+            vars: HashMap::new(),
+            temp: 0,
+            label: 0,
+            line: 0,
+            terminated: false,
+            error: None,
+            path: PathBuf::new(),
+        }
+    }
+
+    fn unsupported(&mut self, line: usize, what: &str) {
+        let path = &self.path;
+        self.error.get_or_insert_with(|| {
+            Diagnostic::new(
+                line,
+                format!("{} is not supported by the llvm backend", what),
+            )
+            .with_file(path)
+        });
+    }
+
+    fn llvm_type(var_type: &VariableType) -> &'static str {
+        match var_type {
+            VariableType::INT32 => "i32",
+            VariableType::STR | VariableType::SLICE(_) => "ptr",
+        }
+    }
+
+    fn ret_type(ret_type: &FunctionType) -> &'static str {
+        match ret_type {
+            FunctionType::VOID => "void",
+            FunctionType::INT => "i32",
+            FunctionType::STR => "ptr",
+        }
+    }
+
+    fn escape(bytes: &[u8]) -> String {
+        let mut out = String::new();
+        for &b in bytes {
+            if b.is_ascii_graphic() && b != b'"' && b != b'\\' || b == b' ' {
+                out.push(b as char);
+            } else {
+                out.push_str(&format!("\\{:02X}", b));
+            }
+        }
+        out
+    }
+
+    fn emit(&mut self, instruction: &str) {
+        if self.terminated {
+            let label = self.new_label();
+            self.builder.push_str(&format!("{}:\n", label));
+            self.terminated = false;
+        }
+        self.builder.push('\t');
+        self.builder.push_str(instruction);
+        self.builder.push('\n');
+    }
+
+    fn new_temp(&mut self) -> String {
+        self.temp += 1;
+        format!("%t{}", self.temp)
+    }
+
+    fn new_label(&mut self) -> String {
+        self.label += 1;
+        format!("l{}", self.label)
+    }
+
+    fn place_label(&mut self, label: &str) {
+        if !self.terminated {
+            self.emit(&format!("br label %{}", label));
+        }
+        self.builder.push_str(&format!("{}:\n", label));
+        self.terminated = false;
+    }
+
+    fn jump(&mut self, instruction: &str) {
+        self.emit(instruction);
+        self.terminated = true;
+    }
+
+    fn branch(&mut self, cond: &str, yes: &str, no: &str) {
+        let temp = self.new_temp();
+        self.emit(&format!("{} = icmp ne i32 {}, 0", temp, cond));
+        self.jump(&format!("br i1 {}, label %{}, label %{}", temp, yes, no));
+    }
+
+    fn overflow_trap(&mut self, overflowed: &str, op: &str) {
+        let message = format!(
+            "edge: attempt to {} with overflow at {}:{}\n",
+            op,
+            self.path.display(),
+            self.line
+        );
+        self.strings.push(message.into_bytes());
+
+        let (trap, ok) = (self.new_label(), self.new_label());
+        self.jump(&format!(
+            "br i1 {}, label %{}, label %{}",
+            overflowed, trap, ok
+        ));
+        self.place_label(&trap);
+        let str = format!(
+            "call void @edge_overflow(ptr @edge_str{})",
+            self.strings.len() - 1
+        );
+        self.emit(&str);
+        self.jump("unreachable");
+        self.place_label(&ok);
+    }
+
+    pub fn generate(mut self, program: &Program) -> Result<String, Box<Diagnostic>> {
+        let mut main = None;
+
+        for module in &program.modules {
+            self.path = module.path.clone();
+            for item in &module.items {
+                match &item.kind {
+                    ItemKind::FUNCTION(func) => {
+                        self.functions
+                            .insert(func.name.clone(), func.ret_type.clone());
+                    }
+                    ItemKind::CONST(constant) => {
+                        if let ConstType::INT32(n) = constant.value {
+                            self.consts.insert(constant.name.clone(), n);
+                        }
+                    }
+                    ItemKind::IMPORT(_) => {}
+                    ItemKind::CCODE(parts) => {
+                        if !includes_only(parts) {
+                            self.unsupported(item.line, "c code");
+                        }
+                    }
+                }
+            }
+        }
+
+        let source = self.source.replace('\\', "\\5C").replace('"', "\\22");
+        self.builder
+            .push_str(&format!("source_filename = \"{}\"\n\n", source));
+        self.builder.push_str(RUNTIME);
+
+        for module in &program.modules {
+            self.path = module.path.clone();
+            for item in &module.items {
+                if let ItemKind::FUNCTION(func) = &item.kind {
+                    if func.name == "main" {
+                        main = Some(func);
+                    }
+                    self.function(func);
+                }
+            }
+        }
+
+        if let Some(main) = main {
+            self.main_wrapper(main);
+        }
+
+        if !self.strings.is_empty() {
+            self.builder.push('\n');
+        }
+        for (i, s) in self.strings.iter().enumerate() {
+            let str = format!(
+                "@edge_str{} = private unnamed_addr constant [{} x i8] c\"{}\\00\"\n",
+                i,
+                s.len() + 1,
+                Self::escape(s)
+            );
+            self.builder.push_str(&str);
+        }
+
+        match self.error {
+            Some(e) => Err(e.into()),
+            None => Ok(self.builder),
+        }
+    }
+
+    fn main_wrapper(&mut self, main: &Function) {
+        let args = if main.params.is_empty() {
+            ""
+        } else {
+            "i32 %argc"
+        };
+
+        self.builder
+            .push_str("\ndefine i32 @main(i32 %argc, ptr %argv) {\nstart:\n");
+        self.terminated = false;
+        self.emit("store i32 %argc, ptr @edge_args_slice");
+        self.emit("%data = getelementptr %edge_slice, ptr @edge_args_slice, i32 0, i32 1");
+        self.emit("store ptr %argv, ptr %data");
+        match main.ret_type {
+            FunctionType::INT => {
+                self.emit(&format!("%r = call i32 @edge__main({})", args));
+                self.emit("ret i32 %r");
+            }
+            _ => {
+                let ret_type = Self::ret_type(&main.ret_type);
+                self.emit(&format!("call {} @edge__main({})", ret_type, args));
+                self.emit("ret i32 0");
+            }
+        }
+        self.builder.push_str("}\n");
+    }
+
+    fn function(&mut self, func: &Function) {
+        self.vars.clear();
+        self.temp = 0;
+        self.label = 0;
+        self.terminated = false;
+
+        let params: Vec<String> = func
+            .params
+            .iter()
+            .map(|(name, var_type)| format!("{} %p.{}", Self::llvm_type(var_type), name))
+            .collect();
+
+        let str = format!(
+            "\ndefine {} @{}({}) {{\nstart:\n",
+            Self::ret_type(&func.ret_type),
+            mangle::item_name(&func.name),
+            params.join(", ")
+        );
+        self.builder.push_str(&str);
+
+        let mut slots: Vec<&String> = func.params.iter().map(|(name, _)| name).collect();
+        Self::collect_slots(&func.body, &mut slots);
+        for slot in slots {
+            self.emit(&format!("%v.{} = alloca i64, align 8", slot));
+        }
+
+        for (name, var_type) in &func.params {
+            let t = Self::llvm_type(var_type);
+            self.emit(&format!("store {} %p.{}, ptr %v.{}", t, name, name));
+            self.vars.insert(name.clone(), var_type.clone());
+        }
+
+        self.block(&func.body);
+
+        if !self.terminated {
+            match func.ret_type {
+                FunctionType::VOID => self.jump("ret void"),
+                FunctionType::INT => self.jump("ret i32 0"),
+                FunctionType::STR => self.jump("ret ptr null"),
+            }
+        }
+        self.builder.push_str("}\n");
+    }
+
+    fn collect_slots<'b>(body: &'b [Stmt], slots: &mut Vec<&'b String>) {
+        for stmt in body {
+            match &stmt.kind {
+                StmtKind::LET(name, _, _) if !slots.contains(&name) => slots.push(name),
+                StmtKind::IF(_, then, otherwise) => {
+                    Self::collect_slots(then, slots);
+                    Self::collect_slots(otherwise, slots);
+                }
+                StmtKind::WHILE(_, body) => Self::collect_slots(body, slots),
+                _ => {}
+            }
+        }
+    }
+
+    fn block(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            self.line = stmt.line;
+            match &stmt.kind {
+                StmtKind::LET(name, var_type, value) => {
+                    let var_type = var_type.clone().unwrap_or(VariableType::INT32);
+                    self.vars.insert(name.clone(), var_type);
+                    if let Some(value) = value {
+                        let (value, _) = self.expr(value);
+                        self.store(name, &value);
+                    }
+                }
+                StmtKind::ASSIGN(name, value) => {
+                    let (value, _) = self.expr(value);
+                    self.store(name, &value);
+                }
+                StmtKind::EXPR(expr) => {
+                    self.expr(expr);
+                }
+                StmtKind::RETURN(value) => match value {
+                    Some(value) => {
+                        let (value, t) = self.expr(value);
+                        self.jump(&format!("ret {} {}", t, value));
+                    }
+                    None => self.jump("ret void"),
+                },
+                StmtKind::IF(cond, then, otherwise) => {
+                    let (cond, _) = self.expr(cond);
+                    let (yes, no, end) = (self.new_label(), self.new_label(), self.new_label());
+                    self.branch(&cond, &yes, &no);
+                    self.place_label(&yes);
+                    self.block(then);
+                    if !self.terminated {
+                        self.jump(&format!("br label %{}", end));
+                    }
+                    self.place_label(&no);
+                    self.block(otherwise);
+                    self.place_label(&end);
+                }
+                StmtKind::WHILE(cond, body) => {
+                    let (start, inner, end) =
+                        (self.new_label(), self.new_label(), self.new_label());
+                    self.place_label(&start);
+                    let (cond, _) = self.expr(cond);
+                    self.branch(&cond, &inner, &end);
+                    self.place_label(&inner);
+                    self.block(body);
+                    if !self.terminated {
+                        self.jump(&format!("br label %{}", start));
+                    }
+                    self.place_label(&end);
+                }
+                StmtKind::CCODE(_) => self.unsupported(stmt.line, "c code"),
+            }
+        }
+    }
+
+    fn store(&mut self, name: &str, value: &str) {
+        let t = Self::llvm_type(&self.vars[name]);
+        self.emit(&format!("store {} {}, ptr %v.{}", t, value, name));
+    }
+
+    fn call(
+        &mut self,
+        name: &str,
+        ret_type: &'static str,
+        args: &[Expr],
+    ) -> (String, &'static str) {
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| {
+                let (value, t) = self.expr(arg);
+                format!("{} {}", t, value)
+            })
+            .collect();
+
+        let call = format!("call {} @{}({})", ret_type, name, args.join(", "));
+        if ret_type == "void" {
+            self.emit(&call);
+            return (String::new(), "void");
+        }
+
+        let temp = self.new_temp();
+        self.emit(&format!("{} = {}", temp, call));
+        (temp, ret_type)
+    }
+
+    fn binary(
+        &mut self,
+        op: BinOp,
+        lhs: &Expr,
+        rhs: &Expr,
+        checked: bool,
+    ) -> (String, &'static str) {
+        let (lhs, _) = self.expr(lhs);
+        let (rhs, _) = self.expr(rhs);
+
+        let instruction = match op {
+            BinOp::ADD => "add",
+            BinOp::SUB => "sub",
+            BinOp::MUL => "mul",
+            BinOp::DIV => "sdiv",
+            BinOp::REM => "srem",
+            BinOp::EQ => "icmp eq",
+            BinOp::NE => "icmp ne",
+            BinOp::LT => "icmp slt",
+            BinOp::LE => "icmp sle",
+            BinOp::GT => "icmp sgt",
+            BinOp::GE => "icmp sge",
+        };
+        let temp = self.new_temp();
+
+        if checked && matches!(op, BinOp::ADD | BinOp::SUB | BinOp::MUL) {
+            let intrinsic = match op {
+                BinOp::ADD => "sadd",
+                BinOp::SUB => "ssub",
+                _ => "smul",
+            };
+            let (result, bad) = (self.new_temp(), self.new_temp());
+            let str = format!(
+                "{} = call {{ i32, i1 }} @llvm.{}.with.overflow.i32(i32 {}, i32 {})",
+                result, intrinsic, lhs, rhs
+            );
+            self.emit(&str);
+            self.emit(&format!(
+                "{} = extractvalue {{ i32, i1 }} {}, 1",
+                bad, result
+            ));
+            self.overflow_trap(&bad, op.overflow_name());
+            self.emit(&format!(
+                "{} = extractvalue {{ i32, i1 }} {}, 0",
+                temp, result
+            ));
+            return (temp, "i32");
+        }
+
+        let plain = rhs.parse::<i32>().is_ok_and(|n| n != 0 && n != -1);
+        if checked && matches!(op, BinOp::DIV | BinOp::REM) && !plain {
+            let (min, minus, bad) = (self.new_temp(), self.new_temp(), self.new_temp());
+            self.emit(&format!("{} = icmp eq i32 {}, -2147483648", min, lhs));
+            self.emit(&format!("{} = icmp eq i32 {}, -1", minus, rhs));
+            self.emit(&format!("{} = and i1 {}, {}", bad, min, minus));
+            self.overflow_trap(&bad, op.overflow_name());
+        }
+        if matches!(op, BinOp::DIV | BinOp::REM) && !plain {
+            let name = if op == BinOp::DIV {
+                "edge_div"
+            } else {
+                "edge_rem"
+            };
+            let str = format!("{} = call i32 @{}(i32 {}, i32 {})", temp, name, lhs, rhs);
+            self.emit(&str);
+            return (temp, "i32");
+        }
+
+        self.emit(&format!("{} = {} i32 {}, {}", temp, instruction, lhs, rhs));
+        if instruction.starts_with("icmp") {
+            let wide = self.new_temp();
+            self.emit(&format!("{} = zext i1 {} to i32", wide, temp));
+            return (wide, "i32");
+        }
+        (temp, "i32")
+    }
+
+    fn expr(&mut self, expr: &Expr) -> (String, &'static str) {
+        match expr {
+            Expr::NUMBER(n) => (n.to_string(), "i32"),
+            Expr::CONST(name) => (self.consts[name].to_string(), "i32"),
+            Expr::STRING(s) => {
+                self.strings.push(s.as_bytes().to_vec());
+                (format!("@edge_str{}", self.strings.len() - 1), "ptr")
+            }
+            Expr::VAR(name) => {
+                let temp = self.new_temp();
+                let t = Self::llvm_type(&self.vars[name]);
+                self.emit(&format!("{} = load {}, ptr %v.{}", temp, t, name));
+                (temp, t)
+            }
+            Expr::CALL(name, args) => {
+                let ret_type = Self::ret_type(&self.functions[name]);
+                self.call(&mangle::item_name(name), ret_type, args)
+            }
+            Expr::BUILTIN(name, args) => match name.as_str() {
+                "putchar" => self.call("putchar", "i32", args),
+                "exit" => self.call("exit", "void", args),
+                "print" => self.call("edge_print", "void", args),
+                "str_len" => self.call("edge_str_len", "i32", args),
+                "str_at" => self.call("edge_str_at", "i32", args),
+                "slice_at" => self.call("edge_slice_at", "ptr", args),
+                "args" => ("@edge_args_slice".to_string(), "ptr"),
+                "wrapping_add" | "wrapping_sub" | "wrapping_mul" => {
+                    self.binary(BinOp::intrinsic(name).unwrap(), &args[0], &args[1], false)
+                }
+                "checked_add" | "checked_sub" | "checked_mul" => {
+                    self.binary(BinOp::intrinsic(name).unwrap(), &args[0], &args[1], true)
+                }
+                "slice_len" => {
+                    let (slice, _) = self.expr(&args[0]);
+                    let temp = self.new_temp();
+                    self.emit(&format!("{} = load i32, ptr {}", temp, slice));
+                    (temp, "i32")
+                }
+                _ => unreachable!("unknown builtin {}", name),
+            },
+            Expr::BINARY(op, lhs, rhs) => self.binary(*op, lhs, rhs, self.overflow_checks),
+            Expr::NEG(value) => {
+                let (value, _) = self.expr(value);
+                if self.overflow_checks {
+                    let bad = self.new_temp();
+                    self.emit(&format!("{} = icmp eq i32 {}, -2147483648", bad, value));
+                    self.overflow_trap(&bad, "negate");
+                }
+                let temp = self.new_temp();
+                self.emit(&format!("{} = sub i32 0, {}", temp, value));
+                (temp, "i32")
+            }
+            Expr::IDENT(_) | Expr::INDEX(..) => {
+                unreachable!("the analyzer resolves identifiers and indexing")
+            }
+        }
+    }
+}
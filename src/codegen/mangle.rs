@@ -0,0 +1,200 @@
+const C_KEYWORDS: [&str; 44] = [
+    "alignas",
+    "alignof",
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "char",
+    "const",
+    "constexpr",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "nullptr",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "static_assert",
+    "struct",
+    "switch",
+    "thread_local",
+    "true",
+    "typedef",
+    "typeof",
+    "typeof_unqual",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+];
+
+const LIBC_NAMES: [&str; 102] = [
+    "abort",
+    "abs",
+    "assert",
+    "atexit",
+    "atoi",
+    "atol",
+    "bsearch",
+    "calloc",
+    "clock",
+    "div",
+    "errno",
+    "exit",
+    "fclose",
+    "fflush",
+    "fgets",
+    "fopen",
+    "fprintf",
+    "fputs",
+    "fread",
+    "free",
+    "fseek",
+    "ftell",
+    "fwrite",
+    "getchar",
+    "getenv",
+    "labs",
+    "longjmp",
+    "malloc",
+    "memchr",
+    "memcmp",
+    "memcpy",
+    "memmove",
+    "memset",
+    "offsetof",
+    "perror",
+    "printf",
+    "putchar",
+    "puts",
+    "qsort",
+    "raise",
+    "rand",
+    "realloc",
+    "remove",
+    "rename",
+    "rewind",
+    "scanf",
+    "setjmp",
+    "signal",
+    "snprintf",
+    "sprintf",
+    "srand",
+    "stderr",
+    "stdin",
+    "stdout",
+    "strcat",
+    "strchr",
+    "strcmp",
+    "strcpy",
+    "strlen",
+    "strncat",
+    "strncmp",
+    "strncpy",
+    "strrchr",
+    "strstr",
+    "strtok",
+    "strtol",
+    "strtoul",
+    "system",
+    "time",
+    "BUFSIZ",
+    "CHAR_BIT",
+    "CHAR_MAX",
+    "CHAR_MIN",
+    "EOF",
+    "EXIT_FAILURE",
+    "EXIT_SUCCESS",
+    "FILE",
+    "FILENAME_MAX",
+    "FOPEN_MAX",
+    "INT_MAX",
+    "INT_MIN",
+    "LLONG_MAX",
+    "LLONG_MIN",
+    "LONG_MAX",
+    "LONG_MIN",
+    "MB_CUR_MAX",
+    "MB_LEN_MAX",
+    "NULL",
+    "RAND_MAX",
+    "SCHAR_MAX",
+    "SCHAR_MIN",
+    "SEEK_CUR",
+    "SEEK_END",
+    "SEEK_SET",
+    "SHRT_MAX",
+    "SHRT_MIN",
+    "TMP_MAX",
+    "UCHAR_MAX",
+    "UINT_MAX",
+    "ULLONG_MAX",
+    "ULONG_MAX",
+    "USHRT_MAX",
+];
+
+pub fn item_name(name: &str) -> String {
+    match name.split_once("::") {
+        Some((module, item)) => format!("edge_{}__{}", module, item),
+        None => format!("edge__{}", name),
+    }
+}
+
+pub fn c_name(name: &str) -> String {
+    let reserved = C_KEYWORDS.contains(&name)
+        || LIBC_NAMES.contains(&name)
+        || name.contains("__")
+        || name.starts_with("edge_")
+        || name.starts_with("local_")
+        || name.ends_with("_t")
+        || (name.starts_with('_')
+            && name[1..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_uppercase()))
+        || (name.starts_with('E')
+            && name[1..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_digit() || c.is_ascii_uppercase()));
+
+    if reserved {
+        format!("local_{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+pub fn c_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for byte in s.bytes() {
+        match byte {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\t' => out.push_str("\\t"),
+            b'\r' => out.push_str("\\r"),
+            b' '..=b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -1 +1,6 @@
+pub mod asm;
 pub mod codegen;
+pub mod llvm;
+pub mod mangle;
+pub mod qbe;
+pub mod wasm;
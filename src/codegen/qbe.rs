@@ -0,0 +1,565 @@
+use crate::ast::{BinOp, Expr, Function, ItemKind, Program, Stmt, StmtKind};
+use crate::codegen::mangle;
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::interpreter::includes_only;
+use crate::symbol_table::{ConstType, FunctionType, VariableType};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const RUNTIME: &str = r#"data $edge_args_slice = align 8 { w 0, w 0, l 0 }
+data $edge_oob_fmt = { b "edge: index out of bounds: the len is %d but the index is %d\n", b 0 }
+data $edge_div_zero_msg = { b "edge: attempt to divide by zero\n", b 0 }
+
+function $edge_out_of_bounds(w %index, w %len) {
+@start
+	call $fflush(l 0)
+	%err =l loadl $stderr
+	call $fprintf(l %err, l $edge_oob_fmt, ..., w %len, w %index)
+	call $exit(w 101)
+	ret
+}
+
+function $edge_divide_by_zero() {
+@start
+	call $fflush(l 0)
+	%err =l loadl $stderr
+	call $fputs(l $edge_div_zero_msg, l %err)
+	call $exit(w 101)
+	ret
+}
+
+function $edge_overflow(l %msg) {
+@start
+	call $fflush(l 0)
+	%err =l loadl $stderr
+	call $fputs(l %msg, l %err)
+	call $exit(w 101)
+	ret
+}
+
+function w $edge_div(w %a, w %b) {
+@start
+	jnz %b, @nonzero, @zero
+@zero
+	call $edge_divide_by_zero()
+	ret 0
+@nonzero
+	%minus =w ceqw %b, -1
+	jnz %minus, @negate, @divide
+@negate
+	%n =w sub 0, %a
+	ret %n
+@divide
+	%r =w div %a, %b
+	ret %r
+}
+
+function w $edge_rem(w %a, w %b) {
+@start
+	jnz %b, @nonzero, @zero
+@zero
+	call $edge_divide_by_zero()
+	ret 0
+@nonzero
+	%minus =w ceqw %b, -1
+	jnz %minus, @negate, @divide
+@negate
+	ret 0
+@divide
+	%r =w rem %a, %b
+	ret %r
+}
+
+function $edge_print(l %s) {
+@start
+	%out =l loadl $stdout
+	call $fputs(l %s, l %out)
+	ret
+}
+
+function w $edge_str_len(l %s) {
+@start
+	%len =w call $strlen(l %s)
+	ret %len
+}
+
+function w $edge_str_at(l %s, w %index) {
+@start
+	%len =w call $edge_str_len(l %s)
+	%ok =w cultw %index, %len
+	jnz %ok, @ok, @bad
+@bad
+	call $edge_out_of_bounds(w %index, w %len)
+	jmp @ok
+@ok
+	%i =l extsw %index
+	%p =l add %s, %i
+	%r =w loadub %p
+	ret %r
+}
+
+function l $edge_slice_at(l %s, w %index) {
+@start
+	%len =w loadw %s
+	%ok =w cultw %index, %len
+	jnz %ok, @ok, @bad
+@bad
+	call $edge_out_of_bounds(w %index, w %len)
+	jmp @ok
+@ok
+	%i =l extsw %index
+	%off =l mul %i, 8
+	%dp =l add %s, 8
+	%data =l loadl %dp
+	%p =l add %data, %off
+	%r =l loadl %p
+	ret %r
+}
+"#;
+
+pub struct QbeCodegen {
+    builder: String,
+    overflow_checks: bool,
+    consts: HashMap<String, i32>,
+    functions: HashMap<String, FunctionType>,
+    strings: Vec<String>,
+    vars: HashMap<String, VariableType>,
+    temp: usize,
+    label: usize,
+    line: usize,
+    terminated: bool,
+    error: Option<Diagnostic>,
+    path: PathBuf,
+}
+
+impl QbeCodegen {
+    pub fn new(overflow_checks: bool) -> Self {
+        Self {
+            builder: String::new(),
+            overflow_checks,
+            consts: HashMap::new(),
+            functions: HashMap::new(),
+            strings: Vec::new(),
+            vars: HashMap::new(),
+            temp: 0,
+            label: 0,
+            line: 0,
+            terminated: false,
+            error: None,
+            path: PathBuf::new(),
+        }
+    }
+
+    fn unsupported(&mut self, line: usize, what: &str) {
+        let path = &self.path;
+        self.error.get_or_insert_with(|| {
+            Diagnostic::new(
+                line,
+                format!("{} is not supported by the qbe backend", what),
+            )
+            .with_file(path)
+        });
+    }
+
+    fn qbe_type(var_type: &VariableType) -> &'static str {
+        match var_type {
+            VariableType::INT32 => "w",
+            VariableType::STR | VariableType::SLICE(_) => "l",
+        }
+    }
+
+    fn emit(&mut self, instruction: &str) {
+        if self.terminated {
+            let label = self.new_label();
+            self.place_label(&label);
+        }
+        self.builder.push('\t');
+        self.builder.push_str(instruction);
+        self.builder.push('\n');
+    }
+
+    fn new_temp(&mut self) -> String {
+        self.temp += 1;
+        format!("%t{}", self.temp)
+    }
+
+    fn new_label(&mut self) -> String {
+        self.label += 1;
+        format!("@l{}", self.label)
+    }
+
+    fn place_label(&mut self, label: &str) {
+        self.builder.push_str(&format!("{}\n", label));
+        self.terminated = false;
+    }
+
+    fn jump(&mut self, instruction: &str) {
+        self.emit(instruction);
+        self.terminated = true;
+    }
+
+    fn overflow_trap(&mut self, overflowed: &str, op: &str) {
+        let message = format!(
+            "edge: attempt to {} with overflow at {}:{}\n",
+            op,
+            self.path.display(),
+            self.line
+        );
+        self.strings.push(message);
+
+        let (trap, ok) = (self.new_label(), self.new_label());
+        self.jump(&format!("jnz {}, {}, {}", overflowed, trap, ok));
+        self.place_label(&trap);
+        let str = format!("call $edge_overflow(l $edge_str{})", self.strings.len() - 1);
+        self.emit(&str);
+        self.place_label(&ok);
+    }
+
+    pub fn generate(mut self, program: &Program) -> Result<String, Box<Diagnostic>> {
+        let mut main = None;
+
+        for module in &program.modules {
+            self.path = module.path.clone();
+            for item in &module.items {
+                match &item.kind {
+                    ItemKind::FUNCTION(func) => {
+                        self.functions
+                            .insert(func.name.clone(), func.ret_type.clone());
+                    }
+                    ItemKind::CONST(constant) => {
+                        if let ConstType::INT32(n) = constant.value {
+                            self.consts.insert(constant.name.clone(), n);
+                        }
+                    }
+                    ItemKind::IMPORT(_) => {}
+                    ItemKind::CCODE(parts) => {
+                        if !includes_only(parts) {
+                            self.unsupported(item.line, "c code");
+                        }
+                    }
+                }
+            }
+        }
+
+        self.builder.push_str(RUNTIME);
+
+        for module in &program.modules {
+            self.path = module.path.clone();
+            for item in &module.items {
+                if let ItemKind::FUNCTION(func) = &item.kind {
+                    if func.name == "main" {
+                        main = Some(func);
+                    }
+                    self.function(func);
+                }
+            }
+        }
+
+        if let Some(main) = main {
+            self.main_wrapper(main);
+        }
+
+        for (i, s) in self.strings.iter().enumerate() {
+            let str = format!(
+                "data $edge_str{} = {{ b {}, b 0 }}\n",
+                i,
+                mangle::c_string(s)
+            );
+            self.builder.push_str(&str);
+        }
+
+        match self.error {
+            Some(e) => Err(e.into()),
+            None => Ok(self.builder),
+        }
+    }
+
+    fn main_wrapper(&mut self, main: &Function) {
+        let args = if main.params.is_empty() {
+            ""
+        } else {
+            "w %argc"
+        };
+
+        self.builder
+            .push_str("\nexport function w $main(w %argc, l %argv) {\n@start\n");
+        self.emit("storew %argc, $edge_args_slice");
+        self.emit("%data =l add $edge_args_slice, 8");
+        self.emit("storel %argv, %data");
+        self.emit(&format!("%r =w call $edge__main({})", args));
+        self.emit("ret %r");
+        self.builder.push_str("}\n");
+    }
+
+    fn function(&mut self, func: &Function) {
+        self.vars.clear();
+        self.temp = 0;
+        self.label = 0;
+        self.terminated = false;
+
+        let ret_type = match func.ret_type {
+            FunctionType::VOID => "",
+            FunctionType::INT => "w ",
+            FunctionType::STR => "l ",
+        };
+        let params: Vec<String> = func
+            .params
+            .iter()
+            .map(|(name, var_type)| format!("{} %p.{}", Self::qbe_type(var_type), name))
+            .collect();
+
+        let str = format!(
+            "\nexport function {}${}({}) {{\n@start\n",
+            ret_type,
+            mangle::item_name(&func.name),
+            params.join(", ")
+        );
+        self.builder.push_str(&str);
+
+        let mut slots: Vec<&String> = func.params.iter().map(|(name, _)| name).collect();
+        Self::collect_slots(&func.body, &mut slots);
+        for slot in slots {
+            self.emit(&format!("%v.{} =l alloc8 8", slot));
+        }
+
+        for (name, var_type) in &func.params {
+            let t = Self::qbe_type(var_type);
+            self.emit(&format!("store{} %p.{}, %v.{}", t, name, name));
+            self.vars.insert(name.clone(), var_type.clone());
+        }
+
+        self.block(&func.body);
+
+        if !self.terminated {
+            match func.ret_type {
+                FunctionType::VOID => self.jump("ret"),
+                _ => self.jump("ret 0"),
+            }
+        }
+        self.builder.push_str("}\n");
+    }
+
+    fn collect_slots<'b>(body: &'b [Stmt], slots: &mut Vec<&'b String>) {
+        for stmt in body {
+            match &stmt.kind {
+                StmtKind::LET(name, _, _) if !slots.contains(&name) => slots.push(name),
+                StmtKind::IF(_, then, otherwise) => {
+                    Self::collect_slots(then, slots);
+                    Self::collect_slots(otherwise, slots);
+                }
+                StmtKind::WHILE(_, body) => Self::collect_slots(body, slots),
+                _ => {}
+            }
+        }
+    }
+
+    fn block(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            self.line = stmt.line;
+            match &stmt.kind {
+                StmtKind::LET(name, var_type, value) => {
+                    let var_type = var_type.clone().unwrap_or(VariableType::INT32);
+                    self.vars.insert(name.clone(), var_type);
+                    if let Some(value) = value {
+                        let (value, _) = self.expr(value);
+                        self.store(name, &value);
+                    }
+                }
+                StmtKind::ASSIGN(name, value) => {
+                    let (value, _) = self.expr(value);
+                    self.store(name, &value);
+                }
+                StmtKind::EXPR(expr) => {
+                    self.expr(expr);
+                }
+                StmtKind::RETURN(value) => match value {
+                    Some(value) => {
+                        let (value, _) = self.expr(value);
+                        self.jump(&format!("ret {}", value));
+                    }
+                    None => self.jump("ret"),
+                },
+                StmtKind::IF(cond, then, otherwise) => {
+                    let (cond, _) = self.expr(cond);
+                    let (yes, no, end) = (self.new_label(), self.new_label(), self.new_label());
+                    self.jump(&format!("jnz {}, {}, {}", cond, yes, no));
+                    self.place_label(&yes);
+                    self.block(then);
+                    if !self.terminated {
+                        self.jump(&format!("jmp {}", end));
+                    }
+                    self.place_label(&no);
+                    self.block(otherwise);
+                    self.place_label(&end);
+                }
+                StmtKind::WHILE(cond, body) => {
+                    let (start, inner, end) =
+                        (self.new_label(), self.new_label(), self.new_label());
+                    self.place_label(&start);
+                    let (cond, _) = self.expr(cond);
+                    self.jump(&format!("jnz {}, {}, {}", cond, inner, end));
+                    self.place_label(&inner);
+                    self.block(body);
+                    self.jump(&format!("jmp {}", start));
+                    self.place_label(&end);
+                }
+                StmtKind::CCODE(_) => self.unsupported(stmt.line, "c code"),
+            }
+        }
+    }
+
+    fn store(&mut self, name: &str, value: &str) {
+        let t = Self::qbe_type(&self.vars[name]);
+        self.emit(&format!("store{} {}, %v.{}", t, value, name));
+    }
+
+    fn call(
+        &mut self,
+        name: &str,
+        ret_type: &'static str,
+        args: &[Expr],
+    ) -> (String, &'static str) {
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| {
+                let (value, t) = self.expr(arg);
+                format!("{} {}", t, value)
+            })
+            .collect();
+
+        let call = format!("call ${}({})", name, args.join(", "));
+        if ret_type.is_empty() {
+            self.emit(&call);
+            return (String::new(), "");
+        }
+
+        let temp = self.new_temp();
+        self.emit(&format!("{} ={} {}", temp, ret_type, call));
+        (temp, ret_type)
+    }
+
+    fn binary(
+        &mut self,
+        op: BinOp,
+        lhs: &Expr,
+        rhs: &Expr,
+        checked: bool,
+    ) -> (String, &'static str) {
+        let (lhs, _) = self.expr(lhs);
+        let (rhs, _) = self.expr(rhs);
+
+        let instruction = match op {
+            BinOp::ADD => "add",
+            BinOp::SUB => "sub",
+            BinOp::MUL => "mul",
+            BinOp::DIV => "div",
+            BinOp::REM => "rem",
+            BinOp::EQ => "ceqw",
+            BinOp::NE => "cnew",
+            BinOp::LT => "csltw",
+            BinOp::LE => "cslew",
+            BinOp::GT => "csgtw",
+            BinOp::GE => "csgew",
+        };
+        let temp = self.new_temp();
+
+        if checked && matches!(op, BinOp::ADD | BinOp::SUB | BinOp::MUL) {
+            let (a, b, wide, back, bad) = (
+                self.new_temp(),
+                self.new_temp(),
+                self.new_temp(),
+                self.new_temp(),
+                self.new_temp(),
+            );
+            self.emit(&format!("{} =l extsw {}", a, lhs));
+            self.emit(&format!("{} =l extsw {}", b, rhs));
+            self.emit(&format!("{} =l {} {}, {}", wide, instruction, a, b));
+            self.emit(&format!("{} =l extsw {}", back, wide));
+            self.emit(&format!("{} =w cnel {}, {}", bad, back, wide));
+            self.overflow_trap(&bad, op.overflow_name());
+            self.emit(&format!("{} =w copy {}", temp, wide));
+            return (temp, "w");
+        }
+
+        let plain = rhs.parse::<i32>().is_ok_and(|n| n != 0 && n != -1);
+        if checked && matches!(op, BinOp::DIV | BinOp::REM) && !plain {
+            let (min, minus, bad) = (self.new_temp(), self.new_temp(), self.new_temp());
+            self.emit(&format!("{} =w ceqw {}, -2147483648", min, lhs));
+            self.emit(&format!("{} =w ceqw {}, -1", minus, rhs));
+            self.emit(&format!("{} =w and {}, {}", bad, min, minus));
+            self.overflow_trap(&bad, op.overflow_name());
+        }
+        if matches!(op, BinOp::DIV | BinOp::REM) && !plain {
+            let str = format!(
+                "{} =w call $edge_{}(w {}, w {})",
+                temp, instruction, lhs, rhs
+            );
+            self.emit(&str);
+        } else {
+            self.emit(&format!("{} =w {} {}, {}", temp, instruction, lhs, rhs));
+        }
+        (temp, "w")
+    }
+
+    fn expr(&mut self, expr: &Expr) -> (String, &'static str) {
+        match expr {
+            Expr::NUMBER(n) => (n.to_string(), "w"),
+            Expr::CONST(name) => (self.consts[name].to_string(), "w"),
+            Expr::STRING(s) => {
+                self.strings.push(s.clone());
+                (format!("$edge_str{}", self.strings.len() - 1), "l")
+            }
+            Expr::VAR(name) => {
+                let temp = self.new_temp();
+                let t = Self::qbe_type(&self.vars[name]);
+                self.emit(&format!("{} ={} load{} %v.{}", temp, t, t, name));
+                (temp, t)
+            }
+            Expr::CALL(name, args) => {
+                let ret_type = match self.functions[name] {
+                    FunctionType::VOID => "",
+                    FunctionType::INT => "w",
+                    FunctionType::STR => "l",
+                };
+                self.call(&mangle::item_name(name), ret_type, args)
+            }
+            Expr::BUILTIN(name, args) => match name.as_str() {
+                "putchar" => self.call("putchar", "w", args),
+                "exit" => self.call("exit", "", args),
+                "print" => self.call("edge_print", "", args),
+                "str_len" => self.call("edge_str_len", "w", args),
+                "str_at" => self.call("edge_str_at", "w", args),
+                "slice_at" => self.call("edge_slice_at", "l", args),
+                "args" => ("$edge_args_slice".to_string(), "l"),
+                "wrapping_add" | "wrapping_sub" | "wrapping_mul" => {
+                    self.binary(BinOp::intrinsic(name).unwrap(), &args[0], &args[1], false)
+                }
+                "checked_add" | "checked_sub" | "checked_mul" => {
+                    self.binary(BinOp::intrinsic(name).unwrap(), &args[0], &args[1], true)
+                }
+                "slice_len" => {
+                    let (slice, _) = self.expr(&args[0]);
+                    let temp = self.new_temp();
+                    self.emit(&format!("{} =w loadw {}", temp, slice));
+                    (temp, "w")
+                }
+                _ => unreachable!("unknown builtin {}", name),
+            },
+            Expr::BINARY(op, lhs, rhs) => self.binary(*op, lhs, rhs, self.overflow_checks),
+            Expr::NEG(value) => {
+                let (value, _) = self.expr(value);
+                if self.overflow_checks {
+                    let bad = self.new_temp();
+                    self.emit(&format!("{} =w ceqw {}, -2147483648", bad, value));
+                    self.overflow_trap(&bad, "negate");
+                }
+                let temp = self.new_temp();
+                self.emit(&format!("{} =w sub 0, {}", temp, value));
+                (temp, "w")
+            }
+            Expr::IDENT(_) | Expr::INDEX(..) => {
+                unreachable!("the analyzer resolves identifiers and indexing")
+            }
+        }
+    }
+}
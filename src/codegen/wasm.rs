@@ -0,0 +1,486 @@
+use crate::ast::{BinOp, Expr, Function, ItemKind, Program, Stmt, StmtKind};
+use crate::codegen::mangle;
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::interpreter::includes_only;
+use crate::symbol_table::{ConstType, FunctionType, VariableType};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const DATA_START: usize = 128;
+
+const RUNTIME: &str = r#"(module
+  (import "env" "write" (func $edge_write (param i32 i32 i32)))
+  (import "env" "exit" (func $edge_exit (param i32)))
+  (data (i32.const 32) "edge: index out of bounds: the len is ")
+  (data (i32.const 70) " but the index is ")
+  (data (i32.const 88) "\0a")
+  (data (i32.const 89) "edge: attempt to divide by zero\0a")
+
+  (func $edge_putchar (param $c i32) (result i32)
+    (i32.store8 (i32.const 16) (local.get $c))
+    (call $edge_write (i32.const 1) (i32.const 16) (i32.const 1))
+    (i32.and (local.get $c) (i32.const 255)))
+
+  (func $edge_str_len (param $s i32) (result i32)
+    (local $i i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (i32.load8_u (i32.add (local.get $s) (local.get $i)))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.get $i))
+
+  (func $edge_print (param $s i32)
+    (call $edge_write (i32.const 1) (local.get $s) (call $edge_str_len (local.get $s))))
+
+  (func $edge_write_int (param $n i32)
+    (local $p i32)
+    (local $neg i32)
+    (local.set $p (i32.const 16))
+    (local.set $neg (i32.lt_s (local.get $n) (i32.const 0)))
+    (if (local.get $neg)
+      (then (local.set $n (i32.sub (i32.const 0) (local.get $n)))))
+    (loop $digit
+      (local.set $p (i32.sub (local.get $p) (i32.const 1)))
+      (i32.store8 (local.get $p) (i32.add (i32.const 48) (i32.rem_u (local.get $n) (i32.const 10))))
+      (local.set $n (i32.div_u (local.get $n) (i32.const 10)))
+      (br_if $digit (local.get $n)))
+    (if (local.get $neg)
+      (then
+        (local.set $p (i32.sub (local.get $p) (i32.const 1)))
+        (i32.store8 (local.get $p) (i32.const 45))))
+    (call $edge_write (i32.const 2) (local.get $p) (i32.sub (i32.const 16) (local.get $p))))
+
+  (func $edge_out_of_bounds (param $index i32) (param $len i32)
+    (call $edge_write (i32.const 2) (i32.const 32) (i32.const 38))
+    (call $edge_write_int (local.get $len))
+    (call $edge_write (i32.const 2) (i32.const 70) (i32.const 18))
+    (call $edge_write_int (local.get $index))
+    (call $edge_write (i32.const 2) (i32.const 88) (i32.const 1))
+    (call $edge_exit (i32.const 101))
+    (unreachable))
+
+  (func $edge_divide_by_zero
+    (call $edge_write (i32.const 2) (i32.const 89) (i32.const 32))
+    (call $edge_exit (i32.const 101))
+    (unreachable))
+
+  (func $edge_overflow (param $msg i32) (param $len i32)
+    (call $edge_write (i32.const 2) (local.get $msg) (local.get $len))
+    (call $edge_exit (i32.const 101))
+    (unreachable))
+
+  (func $edge_narrow (param $r i64) (param $msg i32) (param $len i32) (result i32)
+    (if (i64.ne (local.get $r) (i64.extend_i32_s (i32.wrap_i64 (local.get $r))))
+      (then (call $edge_overflow (local.get $msg) (local.get $len))))
+    (i32.wrap_i64 (local.get $r)))
+
+  (func $edge_div (param $a i32) (param $b i32) (result i32)
+    (if (i32.eqz (local.get $b))
+      (then (call $edge_divide_by_zero)))
+    (if (i32.eq (local.get $b) (i32.const -1))
+      (then (return (i32.sub (i32.const 0) (local.get $a)))))
+    (i32.div_s (local.get $a) (local.get $b)))
+
+  (func $edge_rem (param $a i32) (param $b i32) (result i32)
+    (if (i32.eqz (local.get $b))
+      (then (call $edge_divide_by_zero)))
+    (if (i32.eq (local.get $b) (i32.const -1))
+      (then (return (i32.const 0))))
+    (i32.rem_s (local.get $a) (local.get $b)))
+
+  (func $edge_checked_div (param $a i32) (param $b i32) (param $msg i32) (param $len i32) (result i32)
+    (if (i32.and (i32.eq (local.get $a) (i32.const -2147483648)) (i32.eq (local.get $b) (i32.const -1)))
+      (then (call $edge_overflow (local.get $msg) (local.get $len))))
+    (call $edge_div (local.get $a) (local.get $b)))
+
+  (func $edge_checked_rem (param $a i32) (param $b i32) (param $msg i32) (param $len i32) (result i32)
+    (if (i32.and (i32.eq (local.get $a) (i32.const -2147483648)) (i32.eq (local.get $b) (i32.const -1)))
+      (then (call $edge_overflow (local.get $msg) (local.get $len))))
+    (call $edge_rem (local.get $a) (local.get $b)))
+
+  (func $edge_str_at (param $s i32) (param $index i32) (result i32)
+    (local $len i32)
+    (local.set $len (call $edge_str_len (local.get $s)))
+    (if (i32.ge_u (local.get $index) (local.get $len))
+      (then (call $edge_out_of_bounds (local.get $index) (local.get $len))))
+    (i32.load8_u (i32.add (local.get $s) (local.get $index))))
+"#;
+
+pub struct WasmCodegen {
+    builder: String,
+    overflow_checks: bool,
+    consts: HashMap<String, i32>,
+    functions: HashMap<String, FunctionType>,
+    strings: Vec<(usize, Vec<u8>)>,
+    data_end: usize,
+    depth: usize,
+    label: usize,
+    error: Option<Diagnostic>,
+    path: PathBuf,
+}
+
+impl WasmCodegen {
+    pub fn new(overflow_checks: bool) -> Self {
+        Self {
+            builder: String::new(),
+            overflow_checks,
+            consts: HashMap::new(),
+            functions: HashMap::new(),
+            strings: Vec::new(),
+            data_end: DATA_START,
+            depth: 0,
+            label: 0,
+            error: None,
+            path: PathBuf::new(),
+        }
+    }
+
+    fn unsupported(&mut self, line: usize, what: &str) {
+        let path = &self.path;
+        self.error.get_or_insert_with(|| {
+            Diagnostic::new(
+                line,
+                format!("{} is not supported by the wasm32 target", what),
+            )
+            .with_file(path)
+        });
+    }
+
+    fn escape(bytes: &[u8]) -> String {
+        let mut out = String::new();
+        for &b in bytes {
+            if b.is_ascii_graphic() && b != b'"' && b != b'\\' || b == b' ' {
+                out.push(b as char);
+            } else {
+                out.push_str(&format!("\\{:02x}", b));
+            }
+        }
+        out
+    }
+
+    fn emit(&mut self, instruction: &str) {
+        self.builder.push_str(&"  ".repeat(self.depth));
+        self.builder.push_str(instruction);
+        self.builder.push('\n');
+    }
+
+    fn new_label(&mut self) -> usize {
+        self.label += 1;
+        self.label
+    }
+
+    fn data(&mut self, bytes: Vec<u8>) -> usize {
+        let offset = self.data_end;
+        self.data_end += bytes.len() + 1;
+        self.strings.push((offset, bytes));
+        offset
+    }
+
+    fn overflow_message(&mut self, op: &str, line: usize) {
+        let message = format!(
+            "edge: attempt to {} with overflow at {}:{}\n",
+            op,
+            self.path.display(),
+            line
+        );
+        let len = message.len();
+        let offset = self.data(message.into_bytes());
+        self.emit(&format!("i32.const {}", offset));
+        self.emit(&format!("i32.const {}", len));
+    }
+
+    pub fn generate(mut self, program: &Program) -> Result<String, Box<Diagnostic>> {
+        let mut main = None;
+
+        for module in &program.modules {
+            self.path = module.path.clone();
+            for item in &module.items {
+                match &item.kind {
+                    ItemKind::FUNCTION(func) => {
+                        self.functions
+                            .insert(func.name.clone(), func.ret_type.clone());
+                    }
+                    ItemKind::CONST(constant) => {
+                        if let ConstType::INT32(n) = constant.value {
+                            self.consts.insert(constant.name.clone(), n);
+                        }
+                    }
+                    ItemKind::IMPORT(_) => {}
+                    ItemKind::CCODE(parts) => {
+                        if !includes_only(parts) {
+                            self.unsupported(item.line, "c code");
+                        }
+                    }
+                }
+            }
+        }
+
+        self.builder.push_str(RUNTIME);
+        self.depth = 1;
+
+        for module in &program.modules {
+            self.path = module.path.clone();
+            for item in &module.items {
+                if let ItemKind::FUNCTION(func) = &item.kind {
+                    if func.name == "main" {
+                        main = Some(func);
+                    }
+                    self.function(func);
+                }
+            }
+        }
+
+        if let Some(main) = main {
+            self.main_wrapper(main);
+        }
+
+        if !self.strings.is_empty() {
+            self.builder.push('\n');
+        }
+        for (offset, s) in &self.strings {
+            let str = format!(
+                "  (data (i32.const {}) \"{}\\00\")\n",
+                offset,
+                Self::escape(s)
+            );
+            self.builder.push_str(&str);
+        }
+        let pages = self.data_end.div_ceil(65536);
+        self.builder
+            .push_str(&format!("  (memory (export \"memory\") {})\n)\n", pages));
+
+        match self.error {
+            Some(e) => Err(e.into()),
+            None => Ok(self.builder),
+        }
+    }
+
+    fn main_wrapper(&mut self, main: &Function) {
+        self.builder.push('\n');
+        self.emit("(func (export \"main\") (result i32)");
+        self.depth += 1;
+        if !main.params.is_empty() {
+            self.emit("i32.const 1");
+        }
+        self.emit("call $edge__main");
+        if !matches!(main.ret_type, FunctionType::INT) {
+            self.emit("i32.const 0");
+        }
+        self.depth -= 1;
+        self.emit(")");
+    }
+
+    fn function(&mut self, func: &Function) {
+        let mut header = format!("(func ${}", mangle::item_name(&func.name));
+        for (name, _) in &func.params {
+            header.push_str(&format!(" (param ${} i32)", name));
+        }
+        if !matches!(func.ret_type, FunctionType::VOID) {
+            header.push_str(" (result i32)");
+        }
+
+        self.builder.push('\n');
+        self.emit(&header);
+        self.depth += 1;
+
+        let mut locals: Vec<&String> = Vec::new();
+        Self::collect_locals(&func.body, &func.params, &mut locals);
+        for local in locals {
+            self.emit(&format!("(local ${} i32)", local));
+        }
+
+        self.block(&func.body);
+
+        if !matches!(func.body.last().map(|s| &s.kind), Some(StmtKind::RETURN(_)))
+            && !matches!(func.ret_type, FunctionType::VOID)
+        {
+            self.emit("i32.const 0");
+        }
+        self.depth -= 1;
+        self.emit(")");
+    }
+
+    fn collect_locals<'b>(
+        body: &'b [Stmt],
+        params: &[(String, VariableType)],
+        locals: &mut Vec<&'b String>,
+    ) {
+        for stmt in body {
+            match &stmt.kind {
+                StmtKind::LET(name, _, _)
+                    if !locals.contains(&name) && !params.iter().any(|(p, _)| p == name) =>
+                {
+                    locals.push(name)
+                }
+                StmtKind::IF(_, then, otherwise) => {
+                    Self::collect_locals(then, params, locals);
+                    Self::collect_locals(otherwise, params, locals);
+                }
+                StmtKind::WHILE(_, body) => Self::collect_locals(body, params, locals),
+                _ => {}
+            }
+        }
+    }
+
+    fn block(&mut self, body: &[Stmt]) {
+        for stmt in body {
+            match &stmt.kind {
+                StmtKind::LET(_, _, None) => {}
+                StmtKind::LET(name, _, Some(value)) | StmtKind::ASSIGN(name, value) => {
+                    self.expr(value, stmt.line);
+                    self.emit(&format!("local.set ${}", name));
+                }
+                StmtKind::EXPR(expr) => {
+                    if self.expr(expr, stmt.line) {
+                        self.emit("drop");
+                    }
+                }
+                StmtKind::RETURN(value) => {
+                    if let Some(value) = value {
+                        self.expr(value, stmt.line);
+                    }
+                    self.emit("return");
+                }
+                StmtKind::IF(cond, then, otherwise) => {
+                    self.expr(cond, stmt.line);
+                    self.emit("if");
+                    self.depth += 1;
+                    self.block(then);
+                    self.depth -= 1;
+                    if !otherwise.is_empty() {
+                        self.emit("else");
+                        self.depth += 1;
+                        self.block(otherwise);
+                        self.depth -= 1;
+                    }
+                    self.emit("end");
+                }
+                StmtKind::WHILE(cond, body) => {
+                    let label = self.new_label();
+                    self.emit(&format!("block $break{}", label));
+                    self.depth += 1;
+                    self.emit(&format!("loop $continue{}", label));
+                    self.depth += 1;
+                    self.expr(cond, stmt.line);
+                    self.emit("i32.eqz");
+                    self.emit(&format!("br_if $break{}", label));
+                    self.block(body);
+                    self.emit(&format!("br $continue{}", label));
+                    self.depth -= 1;
+                    self.emit("end");
+                    self.depth -= 1;
+                    self.emit("end");
+                }
+                StmtKind::CCODE(_) => self.unsupported(stmt.line, "c code"),
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], line: usize) {
+        for arg in args {
+            self.expr(arg, line);
+        }
+        self.emit(&format!("call ${}", name));
+    }
+
+    fn binary(&mut self, op: BinOp, lhs: &Expr, rhs: &Expr, line: usize, checked: bool) {
+        if checked && matches!(op, BinOp::ADD | BinOp::SUB | BinOp::MUL) {
+            self.expr(lhs, line);
+            self.emit("i64.extend_i32_s");
+            self.expr(rhs, line);
+            self.emit("i64.extend_i32_s");
+            self.emit(match op {
+                BinOp::ADD => "i64.add",
+                BinOp::SUB => "i64.sub",
+                _ => "i64.mul",
+            });
+            self.overflow_message(op.overflow_name(), line);
+            self.emit("call $edge_narrow");
+            return;
+        }
+
+        self.expr(lhs, line);
+        self.expr(rhs, line);
+        if checked && matches!(op, BinOp::DIV | BinOp::REM) {
+            self.overflow_message(op.overflow_name(), line);
+            self.emit(match op {
+                BinOp::DIV => "call $edge_checked_div",
+                _ => "call $edge_checked_rem",
+            });
+            return;
+        }
+        self.emit(match op {
+            BinOp::ADD => "i32.add",
+            BinOp::SUB => "i32.sub",
+            BinOp::MUL => "i32.mul",
+            BinOp::DIV => "call $edge_div",
+            BinOp::REM => "call $edge_rem",
+            BinOp::EQ => "i32.eq",
+            BinOp::NE => "i32.ne",
+            BinOp::LT => "i32.lt_s",
+            BinOp::LE => "i32.le_s",
+            BinOp::GT => "i32.gt_s",
+            BinOp::GE => "i32.ge_s",
+        });
+    }
+
+    fn expr(&mut self, expr: &Expr, line: usize) -> bool {
+        match expr {
+            Expr::NUMBER(n) => self.emit(&format!("i32.const {}", n)),
+            Expr::CONST(name) => self.emit(&format!("i32.const {}", self.consts[name])),
+            Expr::STRING(s) => {
+                let offset = self.data(s.as_bytes().to_vec());
+                self.emit(&format!("i32.const {}", offset));
+            }
+            Expr::VAR(name) => self.emit(&format!("local.get ${}", name)),
+            Expr::CALL(name, args) => {
+                self.call(&mangle::item_name(name), args, line);
+                return !matches!(self.functions[name], FunctionType::VOID);
+            }
+            Expr::BUILTIN(name, args) => match name.as_str() {
+                "putchar" | "str_len" | "str_at" => {
+                    self.call(&format!("edge_{}", name), args, line)
+                }
+                "exit" | "print" => {
+                    self.call(&format!("edge_{}", name), args, line);
+                    return false;
+                }
+                "wrapping_add" | "wrapping_sub" | "wrapping_mul" => self.binary(
+                    BinOp::intrinsic(name).unwrap(),
+                    &args[0],
+                    &args[1],
+                    line,
+                    false,
+                ),
+                "checked_add" | "checked_sub" | "checked_mul" => self.binary(
+                    BinOp::intrinsic(name).unwrap(),
+                    &args[0],
+                    &args[1],
+                    line,
+                    true,
+                ),
+                "args" => self.unsupported(line, "args()"),
+                _ => self.unsupported(line, "Indexing or taking the len of a slice"),
+            },
+            Expr::BINARY(op, lhs, rhs) => self.binary(*op, lhs, rhs, line, self.overflow_checks),
+            Expr::NEG(value) if self.overflow_checks => {
+                self.emit("i64.const 0");
+                self.expr(value, line);
+                self.emit("i64.extend_i32_s");
+                self.emit("i64.sub");
+                self.overflow_message("negate", line);
+                self.emit("call $edge_narrow");
+            }
+            Expr::NEG(value) => {
+                self.emit("i32.const 0");
+                self.expr(value, line);
+                self.emit("i32.sub");
+            }
+            Expr::IDENT(_) | Expr::INDEX(..) => {
+                unreachable!("the analyzer resolves identifiers and indexing")
+            }
+        }
+        true
+    }
+}
@@ -1,13 +1,99 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct CompilerArgs {
-    #[arg(required = true, value_name = "FILE")]
-    pub input: PathBuf,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[arg(required_unless_present = "explain", value_name = "FILE")]
+    pub input: Option<PathBuf>,
     #[arg(short, long, value_name = "OUTPUT")]
     pub output: Option<PathBuf>,
     #[arg(short, long)]
     pub debug: bool,
+    #[arg(long)]
+    pub lib: bool,
+    #[arg(short = 'g')]
+    pub line_info: bool,
+    #[arg(
+        short = 'O',
+        value_name = "LEVEL",
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u8).range(0..=2)
+    )]
+    pub opt_level: u8,
+    #[arg(long, value_enum)]
+    pub emit: Option<Emit>,
+    #[arg(long, value_enum, default_value_t = Backend::C)]
+    pub backend: Backend,
+    #[arg(long, value_enum)]
+    pub target: Option<Target>,
+    #[arg(long)]
+    pub dump_tokens: bool,
+    #[arg(long)]
+    pub dump_ast: bool,
+    #[arg(long)]
+    pub dump_symbols: bool,
+    #[arg(long, value_enum, default_value_t = ErrorFormat::HUMAN)]
+    pub error_format: ErrorFormat,
+    #[arg(long, value_name = "CODE")]
+    pub explain: Option<String>,
+    #[arg(short = 'W', value_name = "LINT")]
+    pub warn: Vec<String>,
+    #[arg(short = 'A', value_name = "LINT")]
+    pub allow: Vec<String>,
+    #[arg(short = 'D', value_name = "LINT")]
+    pub deny: Vec<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Emit {
+    BYTECODE,
+    LLVM,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    C,
+    ASM,
+    QBE,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    WASM32,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ErrorFormat {
+    HUMAN,
+    JSON,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    Run {
+        #[arg(value_name = "FILE")]
+        input: PathBuf,
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_name = "ARGS"
+        )]
+        args: Vec<String>,
+    },
+    Fmt {
+        #[arg(required = true, value_name = "FILE")]
+        inputs: Vec<PathBuf>,
+        #[arg(long)]
+        check: bool,
+    },
+    Lsp,
 }
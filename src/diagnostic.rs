@@ -0,0 +1,184 @@
+use crate::tokens::Span;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    ERROR,
+    WARNING,
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::ERROR => "error",
+            Severity::WARNING => "warning",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Note {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub code: Option<&'static str>,
+    pub severity: Severity,
+    pub line: Option<usize>,
+    pub message: String,
+    pub notes: Vec<Note>,
+    pub file: Option<PathBuf>,
+    pub span: Option<Span>,
+    pub suggestions: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            code: None,
+            severity: Severity::ERROR,
+            line: Some(line),
+            message: message.into(),
+            notes: Vec::new(),
+            file: None,
+            span: None,
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn global(message: impl Into<String>) -> Self {
+        Self {
+            code: None,
+            severity: Severity::ERROR,
+            line: None,
+            message: message.into(),
+            notes: Vec::new(),
+            file: None,
+            span: None,
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn note(mut self, line: usize, message: impl Into<String>) -> Self {
+        self.notes.push(Note {
+            line,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::ERROR
+    }
+
+    pub fn with_file(mut self, file: &Path) -> Self {
+        if self.file.is_none() {
+            self.file = Some(file.to_path_buf());
+        }
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn suggest(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestions.push(suggestion.into());
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        let code = match self.code {
+            Some(code) => json_string(code),
+            None => "null".to_string(),
+        };
+        let file = match &self.file {
+            Some(file) => json_string(&file.display().to_string()),
+            None => "null".to_string(),
+        };
+        let (column, span) = match self.span {
+            Some(span) => (
+                span.column.to_string(),
+                format!("{{\"start\":{},\"end\":{}}}", span.start, span.end),
+            ),
+            None => ("null".to_string(), "null".to_string()),
+        };
+        let suggestions: Vec<String> = self.suggestions.iter().map(|s| json_string(s)).collect();
+        let notes: Vec<String> = self
+            .notes
+            .iter()
+            .map(|note| {
+                format!(
+                    "{{\"line\":{},\"message\":{}}}",
+                    note.line,
+                    json_string(&note.message)
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"code\":{},\"severity\":\"{}\",\"message\":{},\"file\":{},\"line\":{},\"column\":{},\"span\":{},\"suggestions\":[{}],\"notes\":[{}]}}",
+            code,
+            self.severity.name(),
+            json_string(&self.message),
+            file,
+            self.line
+                .map_or("null".to_string(), |line| line.to_string()),
+            column,
+            span,
+            suggestions.join(","),
+            notes.join(",")
+        )
+    }
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.severity.name().to_uppercase())?;
+        if let Some(code) = self.code {
+            write!(f, "[{}]", code)?;
+        }
+        match self.line {
+            Some(line) => write!(f, " on line {}: {}", line, self.message)?,
+            None => write!(f, ": {}", self.message)?,
+        }
+        for note in &self.notes {
+            write!(f, "\nNOTE on line {}: {}", note.line, note.message)?;
+        }
+        for suggestion in &self.suggestions {
+            write!(f, "\nHELP: {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
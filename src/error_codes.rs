@@ -0,0 +1,1034 @@
+pub const ERROR_CODES: &[(&str, &str)] = &[
+    (
+        "E0001",
+        r##"A character that is not part of the language was found in the source.
+
+Erroneous code example:
+
+    main :: int() {
+        let price = 10$;
+        return 0;
+    }
+
+Edge source may only contain identifiers, numbers, string literals, the
+operators `+ - * / % = == != < <= > >=` and the punctuation
+`; : :: , ( ) { } [ ]`. Remove the character or move it into a string
+literal:
+
+    main :: int() {
+        let price = "10$";
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0002",
+        r##"A string literal was not closed before the end of the file.
+
+Erroneous code example:
+
+    main :: int() {
+        print("hello);
+        return 0;
+    }
+
+Every string literal starts and ends with a `"`. Add the missing quote:
+
+    main :: int() {
+        print("hello");
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0003",
+        r##"A number literal does not fit in an int.
+
+Erroneous code example:
+
+    main :: int() {
+        let big = 3000000000;
+        return 0;
+    }
+
+Numbers are 32 bit signed integers, the largest literal is 2147483647.
+Use a smaller value or compute the value at runtime with the wrapping or
+checked arithmetic builtins.
+"##,
+    ),
+    (
+        "E0005",
+        r##"A `c` block was not closed before the end of the file.
+
+Erroneous code example:
+
+    c {
+        int twice(int n) { return n * 2; }
+
+    main :: int() {
+        return 0;
+    }
+
+The braces inside a `c` block must be balanced, braces inside c string and
+character literals are ignored. Add the missing `}`:
+
+    c {
+        int twice(int n) { return n * 2; }
+    }
+"##,
+    ),
+    (
+        "E0006",
+        r##"The parser expected a specific token but found another one.
+
+Erroneous code example:
+
+    main :: int() {
+        let x = 1
+        return x;
+    }
+
+Here the `;` ending the `let` statement is missing. The message names the
+token that was expected and the one that was found, add or fix the token:
+
+    main :: int() {
+        let x = 1;
+        return x;
+    }
+"##,
+    ),
+    (
+        "E0007",
+        r##"`pub` is not followed by a function or a constant.
+
+Erroneous code example:
+
+    pub import "lib/math.ed";
+
+Only functions and constants can be exported from a module:
+
+    pub const LIMIT = 10;
+
+    pub twice :: int(n: int) {
+        return n * 2;
+    }
+"##,
+    ),
+    (
+        "E0008",
+        r##"`import` is not followed by a file path string.
+
+Erroneous code example:
+
+    import math;
+
+The imported file is given as a string relative to the importing file. Use
+`mod` to import a sibling file by name:
+
+    import "math.ed";
+    mod math;
+"##,
+    ),
+    (
+        "E0009",
+        r##"A token that cannot start a declaration was found at the top level of a
+file.
+
+Erroneous code example:
+
+    let x = 1;
+
+    main :: int() {
+        return x;
+    }
+
+Only functions, constants, imports and `c` code can appear outside of a
+function. Use a constant or move the statement into a function:
+
+    const X = 1;
+
+    main :: int() {
+        return X;
+    }
+"##,
+    ),
+    (
+        "E0010",
+        r##"An imported file does not have the `.ed` extension.
+
+Erroneous code example:
+
+    import "math.txt";
+
+Only Edge source files can be imported, rename the file:
+
+    import "math.ed";
+"##,
+    ),
+    (
+        "E0011",
+        r##"An imported file could not be found or read.
+
+Erroneous code example:
+
+    import "missing.ed";
+
+Import paths are relative to the directory of the importing file. Check the
+path and the permissions of the file.
+"##,
+    ),
+    (
+        "E0012",
+        r##"The name of an imported file is not a valid module name.
+
+Erroneous code example:
+
+    import "2d-math.ed";
+
+The imported file must exist before its name is checked. The file name without its extension becomes the module name. It must start
+with a letter, only contain letters, digits and single underscores and must
+not end with an underscore:
+
+    import "math2d.ed";
+"##,
+    ),
+    (
+        "E0013",
+        r##"Two different files define a module with the same name.
+
+Erroneous code example:
+
+    import "a/util.ed";
+    import "b/util.ed";
+
+Both files would be reachable as `util::`, rename one of them:
+
+    import "a/util.ed";
+    import "b/strings.ed";
+"##,
+    ),
+    (
+        "E0014",
+        r##"A file imports itself, directly or through other files.
+
+Erroneous code example:
+
+    // a.ed
+    import "b.ed";
+
+    // b.ed
+    import "a.ed";
+
+The message lists the files of the cycle. Move the shared declarations into
+a third file imported by both.
+"##,
+    ),
+    (
+        "E0015",
+        r##"`c_comp_append` is called with something other than a string or a
+constant.
+
+Erroneous code example:
+
+    main :: int() {
+        c_comp_append(42);
+        return 0;
+    }
+
+The argument is either a string of c code or the name of a constant holding
+c code:
+
+    const HEADER = "#include <stdio.h>";
+
+    main :: int() {
+        c_comp_append(HEADER);
+        c_comp_append("puts(0);");
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0016",
+        r##"A `${` in c code is not closed.
+
+Erroneous code example:
+
+    main :: int() {
+        let x = 1;
+        c_comp_append("int y = ${x;");
+        return 0;
+    }
+
+`${name}` inserts the c name of an Edge variable, function or constant into
+c code. Close it with `}`:
+
+    main :: int() {
+        let x = 1;
+        c {
+            int y = ${x};
+        }
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0017",
+        r##"A constant is not given a number or a string.
+
+Erroneous code example:
+
+    const LIMIT = OTHER;
+
+Constants are initialized with a literal:
+
+    const LIMIT = 10;
+    const NAME = "edge";
+"##,
+    ),
+    (
+        "E0018",
+        r##"A value was expected but the token cannot start an expression.
+
+Erroneous code example:
+
+    main :: int() {
+        let x = ;
+        return 0;
+    }
+
+Expressions start with a number, a string, a name, `-` or `(`:
+
+    main :: int() {
+        let x = 0;
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0019",
+        r##"An unknown type was used for a variable or a parameter.
+
+Erroneous code example:
+
+    twice :: int(n: number) {
+        return n * 2;
+    }
+
+Variables and parameters have the type `int`, `str` or a slice `[]<type>`:
+
+    twice :: int(n: int) {
+        return n * 2;
+    }
+"##,
+    ),
+    (
+        "E0020",
+        r##"The `main` function does not return an int.
+
+Erroneous code example:
+
+    main :: void() {
+        return;
+    }
+
+The value returned by `main` is the exit code of the program:
+
+    main :: int() {
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0021",
+        r##"An unknown return type was used for a function.
+
+Erroneous code example:
+
+    twice :: number(n: int) {
+        return n * 2;
+    }
+
+Functions return `void`, `int` or `str`:
+
+    twice :: int(n: int) {
+        return n * 2;
+    }
+"##,
+    ),
+    (
+        "E0022",
+        r##"A function does not end with a `return` statement.
+
+Erroneous code example:
+
+    greet :: void() {
+        print("hi");
+    }
+
+Every function ends with an explicit `return`, even void functions:
+
+    greet :: void() {
+        print("hi");
+        return;
+    }
+"##,
+    ),
+    (
+        "E0023",
+        r##"The end of the file was reached inside a block.
+
+Erroneous code example:
+
+    main :: int() {
+        if 1 {
+            return 1;
+        }
+
+Every `{` needs a matching `}`:
+
+    main :: int() {
+        if 1 {
+            return 1;
+        }
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0024",
+        r##"A statement starting with a name is neither an assignment nor a call.
+
+Erroneous code example:
+
+    main :: int() {
+        let x = 1;
+        x + 1;
+        return x;
+    }
+
+A name at the start of a statement is followed by `=` to assign to a
+variable or by `(` to call a function:
+
+    main :: int() {
+        let x = 1;
+        x = x + 1;
+        return x;
+    }
+"##,
+    ),
+    (
+        "E0025",
+        r##"Statements follow a `return` statement in the same block.
+
+Erroneous code example:
+
+    main :: int() {
+        return 0;
+        print("unreachable");
+    }
+
+Statements after `return` can never run, remove them or move them before
+the `return`:
+
+    main :: int() {
+        print("reachable");
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0026",
+        r##"A token that cannot start a statement was found inside a function.
+
+Erroneous code example:
+
+    main :: int() {
+        const X = 1;
+        return X;
+    }
+
+Statements start with `let`, `if`, `while`, `return`, a name or `c`.
+Constants are declared at the top level of a file:
+
+    const X = 1;
+
+    main :: int() {
+        return X;
+    }
+"##,
+    ),
+    (
+        "E0027",
+        r##"An attribute other than `allow` was used.
+
+Erroneous code example:
+
+    #[deny(unused_variables)]
+    main :: int() {
+        return 0;
+    }
+
+Only `#[allow(...)]` attributes are supported, use `-D` on the command line
+to turn warnings into errors:
+
+    #[allow(unused_variables)]
+    main :: int() {
+        let x = 1;
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0028",
+        r##"An unknown lint name was given to `#[allow(...)]`, `-W`, `-A` or `-D`.
+
+Erroneous code example:
+
+    #[allow(unused)]
+    helper :: int() {
+        return 0;
+    }
+
+The known lints are `unused_variables`, `unused_functions`,
+`shadowed_bindings`, `unreachable_code` and `dead_c_comp_append`. The
+name `warnings` stands for every lint:
+
+    #[allow(unused_functions)]
+    helper :: int() {
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0029",
+        r##"A variable declared without a value may be read before it is assigned.
+
+Erroneous code example:
+
+    main :: int(argc: int) {
+        let x: int;
+        if argc > 1 {
+            x = 1;
+        }
+        return x;
+    }
+
+A `let` with a type and without a value declares a variable that must be
+assigned before it is read. The notes show the path that skips the
+assignment, here the condition being false. Assign the variable on every
+path or give it a value when it is declared:
+
+    main :: int(argc: int) {
+        let x: int;
+        if argc > 1 {
+            x = 1;
+        } else {
+            x = 0;
+        }
+        return x;
+    }
+"##,
+    ),
+    (
+        "E0030",
+        r##"A string literal contains an escape sequence that Edge does not know.
+
+Erroneous code example:
+
+    main :: int() {
+        print("C:\data\edge");
+        return 0;
+    }
+
+Inside a string literal a backslash starts an escape sequence. Edge knows
+`\n` (newline), `\t` (tab), `\r` (carriage return), `\0` (nul), `\\`
+(backslash), `\"` and `\'` (quotes) and `\xHH`, two hex digits between 00
+and 7f. Write a literal backslash as `\\`:
+
+    main :: int() {
+        print("C:\\data\\edge");
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0031",
+        r##"A name is defined more than once.
+
+Erroneous code example:
+
+    twice :: int(n: int, n: int) {
+        let x = n;
+        let x = n * 2;
+        return x;
+    }
+
+    main :: int() {
+        return twice(2, 3);
+    }
+
+Functions and constants of a module, parameters of a function and variables
+in scope must all have different names. Rename one of them:
+
+    twice :: int(n: int) {
+        let x = n;
+        let doubled = x * 2;
+        return doubled;
+    }
+
+    main :: int() {
+        return twice(2);
+    }
+"##,
+    ),
+    (
+        "E0032",
+        r##"A function has the name of a builtin function.
+
+Erroneous code example:
+
+    print :: void(n: int) {
+        putchar(n);
+        return;
+    }
+
+Builtin functions like `print`, `putchar`, `exit`, `len` and `args` cannot
+be redefined. Give the function another name:
+
+    print_char :: void(n: int) {
+        putchar(n);
+        return;
+    }
+"##,
+    ),
+    (
+        "E0033",
+        r##"A program has no `main` function.
+
+Erroneous code example:
+
+    start :: int() {
+        return 0;
+    }
+
+Execution starts at `main`, every program that is compiled or run needs
+one:
+
+    main :: int() {
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0034",
+        r##"The `main` function has invalid parameters.
+
+Erroneous code example:
+
+    main :: int(argc: int, argv: str) {
+        return 0;
+    }
+
+`main` takes either no parameters or a single int holding the number of
+command line arguments. Use the `args` builtin to read the arguments:
+
+    main :: int(argc: int) {
+        let argv = args();
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0035",
+        r##"A name does not refer to anything in scope.
+
+Erroneous code example:
+
+    main :: int() {
+        if 1 {
+            let x = 1;
+        }
+        return x + twice(2);
+    }
+
+Variables are only visible inside the block that declares them and functions
+must be defined before they can be called. Declare the name where it is
+used:
+
+    twice :: int(n: int) {
+        return n * 2;
+    }
+
+    main :: int() {
+        let x = 1;
+        return x + twice(2);
+    }
+"##,
+    ),
+    (
+        "E0036",
+        r##"An item of a module that is not imported is used.
+
+Erroneous code example:
+
+    main :: int() {
+        return math::twice(2);
+    }
+
+Import the file of the module before using its items:
+
+    import "math.ed";
+
+    main :: int() {
+        return math::twice(2);
+    }
+"##,
+    ),
+    (
+        "E0037",
+        r##"A private item of another module is used.
+
+Erroneous code example:
+
+    // math.ed
+    twice :: int(n: int) {
+        return n * 2;
+    }
+
+    // main.ed
+    import "math.ed";
+
+    main :: int() {
+        return math::twice(2);
+    }
+
+Functions and constants are private to their module unless they are marked
+with `pub`:
+
+    // math.ed
+    pub twice :: int(n: int) {
+        return n * 2;
+    }
+"##,
+    ),
+    (
+        "E0038",
+        r##"A value has a different type than expected.
+
+Erroneous code example:
+
+    main :: int() {
+        let n: int = "ten";
+        return n[0];
+    }
+
+Values must have the type of the variable, parameter or return type they
+are used for, and only strings and slices can be indexed:
+
+    main :: int() {
+        let s: str = "ten";
+        return s[0];
+    }
+"##,
+    ),
+    (
+        "E0039",
+        r##"A function that returns nothing is used as a value.
+
+Erroneous code example:
+
+    greet :: void() {
+        print("hello");
+        return;
+    }
+
+    main :: int() {
+        let x = greet();
+        return 0;
+    }
+
+A `void` function can only be called as a statement:
+
+    main :: int() {
+        greet();
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0040",
+        r##"A function is called with the wrong number of arguments.
+
+Erroneous code example:
+
+    add :: int(a: int, b: int) {
+        return a + b;
+    }
+
+    main :: int() {
+        return add(1);
+    }
+
+Pass one argument for every parameter of the function:
+
+    main :: int() {
+        return add(1, 2);
+    }
+"##,
+    ),
+    (
+        "E0041",
+        r##"A `return` statement does not match the return type of the function.
+
+Erroneous code example:
+
+    greet :: void() {
+        print("hello");
+        return 0;
+    }
+
+    main :: int() {
+        return;
+    }
+
+Functions returning a value must return one with every `return`, `void`
+functions return without a value:
+
+    greet :: void() {
+        print("hello");
+        return;
+    }
+
+    main :: int() {
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0042",
+        r##"A value is assigned to a constant.
+
+Erroneous code example:
+
+    const LIMIT = 10;
+
+    main :: int() {
+        LIMIT = 20;
+        return LIMIT;
+    }
+
+Constants cannot change. Copy the constant into a variable instead:
+
+    const LIMIT = 10;
+
+    main :: int() {
+        let limit = LIMIT;
+        limit = 20;
+        return limit;
+    }
+"##,
+    ),
+    (
+        "E0043",
+        r##"A string constant is used as a value.
+
+Erroneous code example:
+
+    const HEADER = "#include <stdio.h>";
+
+    main :: int() {
+        print(HEADER);
+        return 0;
+    }
+
+String constants hold c code and can only be passed to `c_comp_append`. Use
+a string literal for text:
+
+    main :: int() {
+        print("#include <stdio.h>");
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0044",
+        r##"A variable is passed to `c_comp_append`.
+
+Erroneous code example:
+
+    main :: int() {
+        let x = 1;
+        c_comp_append(x);
+        return 0;
+    }
+
+`c_comp_append` only accepts string literals and constants. Insert the c
+name of a variable with `${name}`:
+
+    main :: int() {
+        let x = 1;
+        c_comp_append("printf(\"%d\", ${x});");
+        return 0;
+    }
+"##,
+    ),
+    (
+        "E0045",
+        r##"An expression of constants overflows an int.
+
+Erroneous code example:
+
+    const MAX = 2147483647;
+
+    main :: int() {
+        return MAX + 1;
+    }
+
+Ints are 32 bit and overflowing them is an error. Use `wrapping_add`,
+`wrapping_sub` or `wrapping_mul` when wrapping around is intended:
+
+    main :: int() {
+        return wrapping_add(MAX, 1);
+    }
+"##,
+    ),
+    (
+        "E0046",
+        r##"An expression of constants divides by zero.
+
+Erroneous code example:
+
+    const PARTS = 0;
+
+    main :: int() {
+        return 10 / PARTS;
+    }
+
+Division and remainder by zero have no result. Check the divisor or use a
+different constant:
+
+    const PARTS = 2;
+
+    main :: int() {
+        return 10 / PARTS;
+    }
+"##,
+    ),
+    (
+        "W0001",
+        r##"A variable is never read or a parameter is never used. This is the
+`unused_variables` lint.
+
+Example:
+
+    twice :: int(n: int, scale: int) {
+        let x = 1;
+        return n * 2;
+    }
+
+Assigning to a variable does not count as reading it. Remove the variable,
+prefix its name with an underscore or allow the lint on the statement:
+
+    twice :: int(n: int, _scale: int) {
+        #[allow(unused_variables)]
+        let x = 1;
+        return n * 2;
+    }
+"##,
+    ),
+    (
+        "W0002",
+        r##"A function that is not `pub` and not `main` is never called. This is the
+`unused_functions` lint.
+
+Example:
+
+    helper :: int() {
+        return 1;
+    }
+
+    main :: int() {
+        return 0;
+    }
+
+Call the function, make it `pub` if another module uses it, remove it,
+prefix its name with an underscore or allow the lint on the function:
+
+    #[allow(unused_functions)]
+    helper :: int() {
+        return 1;
+    }
+"##,
+    ),
+    (
+        "W0003",
+        r##"A variable or a parameter has the same name as a constant of its module.
+This is the `shadowed_bindings` lint.
+
+Example:
+
+    const LIMIT = 10;
+
+    main :: int() {
+        let LIMIT = 5;
+        return LIMIT;
+    }
+
+Inside the function the name refers to the variable and the constant can no
+longer be used. Rename the variable:
+
+    const LIMIT = 10;
+
+    main :: int() {
+        let limit = 5;
+        return limit + LIMIT;
+    }
+"##,
+    ),
+    (
+        "W0004",
+        r##"A statement can never run. This is the `unreachable_code` lint.
+
+Example:
+
+    sign :: int(n: int) {
+        if n < 0 {
+            return -1;
+        } else {
+            return 1;
+        }
+        print("done");
+        return 0;
+    }
+
+Code following a `return`, a call to `exit`, an `if` whose branches all
+return or a `while` loop with a constant true condition is unreachable, as
+well as the body of an `if` or a `while` with a constant false condition.
+The `return` that every function must end with is not reported.
+"##,
+    ),
+    (
+        "W0005",
+        r##"A `c_comp_append` or a `c` block adds no code. This is the
+`dead_c_comp_append` lint.
+
+Example:
+
+    const EXTRA = "";
+
+    main :: int() {
+        c_comp_append(EXTRA);
+        return 0;
+    }
+
+Remove the statement or give it some code to add.
+"##,
+    ),
+];
+
+pub fn explain(code: &str) -> Option<&'static str> {
+    ERROR_CODES
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
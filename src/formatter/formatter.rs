@@ -0,0 +1,405 @@
+use crate::ast::{
+    BinOp, CPart, Comments, Expr, Function, Import, Item, ItemKind, Module, Stmt, StmtKind,
+};
+use crate::symbol_table::{ConstType, params_to_string};
+use crate::tokens::{Span, Trivia};
+
+const INDENT: &str = "    ";
+
+pub struct Formatter<'a> {
+    source: &'a str,
+    out: String,
+    depth: usize,
+}
+
+impl<'a> Formatter<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            out: String::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn format(mut self, module: &Module) -> String {
+        for (i, item) in module.items.iter().enumerate() {
+            let is_fn = |item: &Item| matches!(item.kind, ItemKind::FUNCTION(_));
+            if i > 0 && (is_fn(item) || is_fn(&module.items[i - 1])) {
+                self.blank();
+            }
+            self.item(item);
+        }
+        self.trivia(&module.comments);
+
+        while self.out.ends_with("\n\n") {
+            self.out.pop();
+        }
+        self.out
+    }
+
+    fn line(&mut self, text: &str) {
+        self.out.push_str(&INDENT.repeat(self.depth));
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn blank(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") && !self.out.ends_with("{\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn trivia(&mut self, trivia: &[Trivia]) {
+        for piece in trivia {
+            match piece {
+                Trivia::COMMENT(text) | Trivia::TRAILING(text) => self.line(text),
+                Trivia::BLANKLINE => self.blank(),
+            }
+        }
+    }
+
+    fn end(&mut self, comments: &Comments) {
+        if let Some(text) = &comments.trailing {
+            self.out.pop();
+            self.out.push(' ');
+            self.out.push_str(text);
+            self.out.push('\n');
+        }
+        self.trivia(&comments.after);
+    }
+
+    fn allow(&mut self, allow: &[String]) {
+        if !allow.is_empty() {
+            self.line(&format!("#[allow({})]", allow.join(", ")));
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        self.trivia(&item.comments.leading);
+        self.allow(&item.allow);
+
+        match &item.kind {
+            ItemKind::FUNCTION(func) => self.function(func),
+            ItemKind::CONST(constant) => {
+                let value = match &constant.value {
+                    ConstType::INT32(n) => n.to_string(),
+                    ConstType::STRING(s) => string(s),
+                };
+                self.line(&format!(
+                    "{}const {} = {};",
+                    public(constant.public),
+                    constant.name,
+                    value
+                ));
+            }
+            ItemKind::CCODE(parts) => self.c_code(parts, item.span),
+            ItemKind::IMPORT(Import::FILE(path)) => self.line(&format!("import {};", string(path))),
+            ItemKind::IMPORT(Import::MOD(name)) => self.line(&format!("mod {};", name)),
+        }
+
+        self.end(&item.comments);
+    }
+
+    fn function(&mut self, func: &Function) {
+        let params = if func.params.is_empty() {
+            String::new()
+        } else {
+            format!("({})", params_to_string(&func.params))
+        };
+        self.open(&format!(
+            "{}{} :: {}{}",
+            public(func.public),
+            func.name,
+            func.ret_type,
+            params
+        ));
+        self.body(&func.body);
+    }
+
+    fn open(&mut self, header: &str) {
+        self.out.push_str(&INDENT.repeat(self.depth));
+        self.out.push_str(header);
+    }
+
+    fn body(&mut self, body: &[Stmt]) {
+        if body.is_empty() {
+            self.out.push_str(" {}\n");
+            return;
+        }
+
+        self.out.push_str(" {\n");
+        self.depth += 1;
+        for stmt in body {
+            self.stmt(stmt);
+        }
+        while self.out.ends_with("\n\n") {
+            self.out.pop();
+        }
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        self.trivia(&stmt.comments.leading);
+        self.allow(&stmt.allow);
+
+        match &stmt.kind {
+            StmtKind::LET(name, var_type, value) => {
+                let mut text = format!("let {}", name);
+                if let Some(var_type) = var_type {
+                    text.push_str(&format!(": {}", var_type));
+                }
+                if let Some(value) = value {
+                    text.push_str(&format!(" = {}", expr(value)));
+                }
+                self.line(&format!("{};", text));
+            }
+            StmtKind::ASSIGN(name, value) => self.line(&format!("{} = {};", name, expr(value))),
+            StmtKind::EXPR(value) => self.line(&format!("{};", expr(value))),
+            StmtKind::RETURN(None) => self.line("return;"),
+            StmtKind::RETURN(Some(value)) => self.line(&format!("return {};", expr(value))),
+            StmtKind::IF(cond, then, otherwise) => {
+                self.open(&format!("if {}", expr(cond)));
+                self.body(then);
+
+                let mut otherwise = otherwise;
+                while !otherwise.is_empty() {
+                    self.out.pop();
+                    match otherwise.as_slice() {
+                        [
+                            Stmt {
+                                kind: StmtKind::IF(cond, then, rest),
+                                allow,
+                                comments,
+                                ..
+                            },
+                        ] if allow.is_empty()
+                            && comments.leading.is_empty()
+                            && comments.trailing.is_none() =>
+                        {
+                            self.out.push_str(&format!(" else if {}", expr(cond)));
+                            self.body(then);
+                            otherwise = rest;
+                        }
+                        _ => {
+                            self.out.push_str(" else");
+                            self.body(otherwise);
+                            break;
+                        }
+                    }
+                }
+            }
+            StmtKind::WHILE(cond, body) => {
+                self.open(&format!("while {}", expr(cond)));
+                self.body(body);
+            }
+            StmtKind::CCODE(parts) => self.c_code(parts, stmt.span),
+        }
+
+        self.end(&stmt.comments);
+    }
+
+    fn c_code(&mut self, parts: &[CPart], span: Span) {
+        let code: String = parts
+            .iter()
+            .map(|part| match part {
+                CPart::TEXT(text) => text.clone(),
+                CPart::IDENT(name) | CPart::ITEM(name) => format!("${{{}}}", name),
+                CPart::CONST(name) => name.clone(),
+            })
+            .collect();
+
+        if &self.source[span.start..span.end] != "c" {
+            match parts {
+                [CPart::CONST(name)] => self.line(&format!("c_comp_append({});", name)),
+                _ => self.line(&format!("c_comp_append({});", string(&code))),
+            }
+            return;
+        }
+
+        if code.trim().is_empty() {
+            self.line("c {}");
+        } else {
+            let margin = code
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.len() - line.trim_start().len())
+                .min()
+                .unwrap_or(0);
+
+            self.line("c {");
+            self.depth += 1;
+            for line in code.lines() {
+                if line.trim().is_empty() {
+                    self.out.push('\n');
+                } else {
+                    self.line(line[margin..].trim_end());
+                }
+            }
+            self.depth -= 1;
+            self.line("}");
+        }
+    }
+}
+
+fn public(public: bool) -> &'static str {
+    if public { "pub " } else { "" }
+}
+
+fn string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\0' => out.push_str("\\0"),
+            c if c.is_ascii_control() => out.push_str(&format!("\\x{:02x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::BINARY(op, _, _) => match op {
+            BinOp::ADD | BinOp::SUB => 2,
+            BinOp::MUL | BinOp::DIV | BinOp::REM => 3,
+            _ => 1,
+        },
+        Expr::NEG(_) => 4,
+        _ => 5,
+    }
+}
+
+fn operand(value: &Expr, min: u8) -> String {
+    if precedence(value) < min {
+        format!("({})", expr(value))
+    } else {
+        expr(value)
+    }
+}
+
+fn expr(value: &Expr) -> String {
+    match value {
+        Expr::NUMBER(n) => n.to_string(),
+        Expr::STRING(s) => string(s),
+        Expr::IDENT(name) | Expr::VAR(name) | Expr::CONST(name) => name.clone(),
+        Expr::CALL(name, args) | Expr::BUILTIN(name, args) => {
+            let args: Vec<String> = args.iter().map(expr).collect();
+            format!("{}({})", name, args.join(", "))
+        }
+        Expr::INDEX(base, index) => format!("{}[{}]", operand(base, 5), expr(index)),
+        Expr::BINARY(op, lhs, rhs) => {
+            let min = precedence(value);
+            format!(
+                "{} {} {}",
+                operand(lhs, min),
+                op.symbol(),
+                operand(rhs, min + 1)
+            )
+        }
+        Expr::NEG(inner) => format!("-{}", operand(inner, 4)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    const UNFORMATTED: &str = r##"// leading comment
+import "util.ed";
+
+pub const   NAME="a\tb\\c\"d"; // trailing const comment
+const HEADER = "#include <stdio.h>";
+c_comp_append(HEADER);
+
+#[allow(unused_variables)]
+add::int(a:int,b:int){
+    // inside body
+    let x = a + // inline
+        b; // trailing
+    let y:int;
+    let s: []str = args();
+    y=-(x*(2+3))%7-(-b);
+    if x>y{return x;}else{
+        while x<y { x = x+1; }
+    }
+    c {
+        int   z = ${x};
+    }
+    c_comp_append("printf(\"%d\", ${y});");
+    return x;
+}
+
+main :: int() {
+
+    print(NAME);
+    return add(1, // first
+        2) + len(args()[0]);
+}
+"##;
+
+    const FORMATTED: &str = r##"// leading comment
+import "util.ed";
+
+pub const NAME = "a\tb\\c\"d"; // trailing const comment
+const HEADER = "#include <stdio.h>";
+c_comp_append(HEADER);
+
+#[allow(unused_variables)]
+add :: int(a: int, b: int) {
+    // inside body
+    let x = a + b; // inline
+    // trailing
+    let y: int;
+    let s: []str = args();
+    y = -(x * (2 + 3)) % 7 - -b;
+    if x > y {
+        return x;
+    } else {
+        while x < y {
+            x = x + 1;
+        }
+    }
+    c {
+        int   z = ${x};
+    }
+    c_comp_append("printf(\"%d\", ${y});");
+    return x;
+}
+
+main :: int {
+    print(NAME);
+    return add(1, 2) + len(args()[0]); // first
+}
+"##;
+
+    fn format(source: &str) -> String {
+        crate::format(source, PathBuf::from("test.ed")).unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(format(UNFORMATTED), FORMATTED);
+    }
+
+    #[test]
+    fn idempotent() {
+        assert_eq!(format(FORMATTED), FORMATTED);
+        assert_eq!(format(&format(UNFORMATTED)), format(UNFORMATTED));
+    }
+
+    #[test]
+    fn comments_inside_expressions_move_to_the_end_of_the_line() {
+        let source = "main :: int {\n    let x = 1 + // one\n        2 + // two\n        3;\n    return x;\n}\n";
+        let formatted =
+            "main :: int {\n    let x = 1 + 2 + 3; // one\n    // two\n    return x;\n}\n";
+        assert_eq!(format(source), formatted);
+        assert_eq!(format(formatted), formatted);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::ast::{BinOp, CPart, Expr, Function, ItemKind, Program, Stmt, StmtKind};
+use crate::diagnostic::Diagnostic;
+use crate::symbol_table::ConstType;
+use std::collections::HashMap;
+use std::io::Write;
+use std::mem;
+use std::path::Path;
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    INT(i32),
+    STR(Vec<u8>),
+    SLICE(Vec<Vec<u8>>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Halt {
+    EXIT(i32),
+    TRAP(String),
+}
+
+impl Halt {
+    pub fn code(&self) -> i32 {
+        match self {
+            Halt::EXIT(code) => *code,
+            Halt::TRAP(_) => 101,
+        }
+    }
+}
+
+pub struct Interpreter<'a, W: Write> {
+    functions: HashMap<String, &'a Function>,
+    files: HashMap<String, String>,
+    file: String,
+    line: usize,
+    consts: HashMap<String, i32>,
+    args: Vec<Vec<u8>>,
+    out: W,
+}
+
+impl<'a, W: Write> Interpreter<'a, W> {
+    pub fn new(program: &'a Program, args: Vec<String>, out: W) -> Result<Self, Box<Diagnostic>> {
+        let mut functions = HashMap::new();
+        let mut files = HashMap::new();
+        let mut consts = HashMap::new();
+
+        for module in &program.modules {
+            for item in &module.items {
+                match &item.kind {
+                    ItemKind::FUNCTION(func) => {
+                        if let Some(line) = c_code_line(&func.body) {
+                            return Err(c_code_error(line, &module.path).into());
+                        }
+                        functions.insert(func.name.clone(), func);
+                        files.insert(func.name.clone(), module.path.display().to_string());
+                    }
+                    ItemKind::CONST(constant) => {
+                        if let ConstType::INT32(n) = constant.value {
+                            consts.insert(constant.name.clone(), n);
+                        }
+                    }
+                    ItemKind::IMPORT(_) => {}
+                    ItemKind::CCODE(parts) => {
+                        if !includes_only(parts) {
+                            return Err(c_code_error(item.line, &module.path).into());
+                        }
+                    }
+                }
+            }
+        }
+
+        if !functions.contains_key("main") {
+            let error = Diagnostic::global("The interpreter needs a 'main :: int' function to run");
+            let error = match program.modules.last() {
+                Some(root) => error.with_file(&root.path),
+                None => error,
+            };
+            return Err(error.into());
+        }
+
+        Ok(Self {
+            functions,
+            files,
+            file: String::new(),
+            line: 0,
+            consts,
+            args: args.into_iter().map(String::into_bytes).collect(),
+            out,
+        })
+    }
+
+    pub fn run(&mut self) -> Halt {
+        let main = self.functions["main"];
+
+        let args = if main.params.is_empty() {
+            Vec::new()
+        } else {
+            vec![Value::INT(self.args.len() as i32)]
+        };
+
+        let halt = match self.call(main, args) {
+            Ok(Some(Value::INT(n))) => Halt::EXIT(n),
+            Ok(_) => Halt::EXIT(0),
+            Err(halt) => halt,
+        };
+        let _ = self.out.flush();
+        halt
+    }
+
+    fn call(&mut self, func: &Function, args: Vec<Value>) -> Result<Option<Value>, Halt> {
+        let mut vars: HashMap<String, Value> = func
+            .params
+            .iter()
+            .map(|(name, _)| name.clone())
+            .zip(args)
+            .collect();
+
+        let file = self.files[&func.name].clone();
+        let caller = (mem::replace(&mut self.file, file), self.line);
+        let value = self.block(&func.body, &mut vars)?.flatten();
+        (self.file, self.line) = caller;
+        Ok(value)
+    }
+
+    fn arithmetic(&self, op: BinOp, lhs: i32, rhs: i32) -> Result<i32, Halt> {
+        if matches!(op, BinOp::DIV | BinOp::REM) && rhs == 0 {
+            return Err(divide_by_zero());
+        }
+        checked_binary(op, lhs, rhs)
+            .ok_or_else(|| overflow(op.overflow_name(), &self.file, self.line))
+    }
+
+    fn block(
+        &mut self,
+        body: &[Stmt],
+        vars: &mut HashMap<String, Value>,
+    ) -> Result<Option<Option<Value>>, Halt> {
+        for stmt in body {
+            self.line = stmt.line;
+            match &stmt.kind {
+                StmtKind::LET(_, _, None) => {}
+                StmtKind::LET(name, _, Some(value)) | StmtKind::ASSIGN(name, value) => {
+                    let value = self.value(value, vars)?;
+                    vars.insert(name.clone(), value);
+                }
+                StmtKind::EXPR(expr) => {
+                    self.expr(expr, vars)?;
+                }
+                StmtKind::RETURN(value) => {
+                    let value = match value {
+                        Some(value) => Some(self.value(value, vars)?),
+                        None => None,
+                    };
+                    return Ok(Some(value));
+                }
+                StmtKind::IF(cond, then, otherwise) => {
+                    let body = if self.int(cond, vars)? != 0 {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    if let Some(value) = self.block(body, vars)? {
+                        return Ok(Some(value));
+                    }
+                }
+                StmtKind::WHILE(cond, body) => loop {
+                    self.line = stmt.line;
+                    if self.int(cond, vars)? == 0 {
+                        break;
+                    }
+                    if let Some(value) = self.block(body, vars)? {
+                        return Ok(Some(value));
+                    }
+                },
+                StmtKind::CCODE(_) => unreachable!("Interpreter::new rejects c code"),
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn value(&mut self, expr: &Expr, vars: &HashMap<String, Value>) -> Result<Value, Halt> {
+        Ok(self
+            .expr(expr, vars)?
+            .expect("the analyzer only allows expressions with a value here"))
+    }
+
+    fn int(&mut self, expr: &Expr, vars: &HashMap<String, Value>) -> Result<i32, Halt> {
+        match self.value(expr, vars)? {
+            Value::INT(n) => Ok(n),
+            value => unreachable!("expected an int but got {:?}", value),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, vars: &HashMap<String, Value>) -> Result<Option<Value>, Halt> {
+        let value = match expr {
+            Expr::NUMBER(n) => Value::INT(*n),
+            Expr::STRING(s) => Value::STR(s.as_bytes().to_vec()),
+            Expr::VAR(name) => vars[name].clone(),
+            Expr::CONST(name) => Value::INT(self.consts[name]),
+            Expr::CALL(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| self.value(a, vars))
+                    .collect::<Result<Vec<Value>, Halt>>()?;
+                let func = self.functions[name];
+                return self.call(func, args);
+            }
+            Expr::BUILTIN(name, args) => return self.builtin(name, args, vars),
+            Expr::BINARY(op, lhs, rhs) => {
+                let lhs = self.int(lhs, vars)?;
+                let rhs = self.int(rhs, vars)?;
+                Value::INT(self.arithmetic(*op, lhs, rhs)?)
+            }
+            Expr::NEG(value) => match self.int(value, vars)?.checked_neg() {
+                Some(n) => Value::INT(n),
+                None => return Err(overflow("negate", &self.file, self.line)),
+            },
+            Expr::IDENT(_) | Expr::INDEX(..) => {
+                unreachable!("the analyzer resolves identifiers and indexing")
+            }
+        };
+        Ok(Some(value))
+    }
+
+    fn builtin(
+        &mut self,
+        name: &str,
+        args: &[Expr],
+        vars: &HashMap<String, Value>,
+    ) -> Result<Option<Value>, Halt> {
+        let value = match name {
+            "putchar" => {
+                let c = self.int(&args[0], vars)?;
+                let _ = self.out.write_all(&[c as u8]);
+                Value::INT(c as u8 as i32)
+            }
+            "exit" => return Err(Halt::EXIT(self.int(&args[0], vars)?)),
+            "args" => Value::SLICE(self.args.clone()),
+            "print" => {
+                if let Value::STR(s) = self.value(&args[0], vars)? {
+                    let _ = self.out.write_all(c_str(&s));
+                }
+                return Ok(None);
+            }
+            "str_len" | "slice_len" => {
+                let len = match self.value(&args[0], vars)? {
+                    Value::STR(s) => c_str(&s).len(),
+                    Value::SLICE(s) => s.len(),
+                    Value::INT(_) => unreachable!(),
+                };
+                Value::INT(len as i32)
+            }
+            "str_at" | "slice_at" => {
+                let base = self.value(&args[0], vars)?;
+                let index = self.int(&args[1], vars)?;
+                let len = match &base {
+                    Value::STR(s) => c_str(s).len(),
+                    Value::SLICE(s) => s.len(),
+                    Value::INT(_) => unreachable!(),
+                };
+                if index < 0 || index as usize >= len {
+                    return Err(out_of_bounds(len, index));
+                }
+
+                match base {
+                    Value::STR(s) => Value::INT(s[index as usize] as i32),
+                    Value::SLICE(mut s) => Value::STR(s.swap_remove(index as usize)),
+                    Value::INT(_) => unreachable!(),
+                }
+            }
+            "wrapping_add" | "wrapping_sub" | "wrapping_mul" => {
+                let lhs = self.int(&args[0], vars)?;
+                let rhs = self.int(&args[1], vars)?;
+                return Ok(binary(BinOp::intrinsic(name).unwrap(), lhs, rhs).map(Value::INT));
+            }
+            "checked_add" | "checked_sub" | "checked_mul" => {
+                let lhs = self.int(&args[0], vars)?;
+                let rhs = self.int(&args[1], vars)?;
+                Value::INT(self.arithmetic(BinOp::intrinsic(name).unwrap(), lhs, rhs)?)
+            }
+            _ => unreachable!("unknown builtin {}", name),
+        };
+        Ok(Some(value))
+    }
+}
+
+fn c_code_error(line: usize, path: &Path) -> Diagnostic {
+    Diagnostic::new(
+        line,
+        "c code cannot be run by the interpreter, compile the program instead",
+    )
+    .with_file(path)
+}
+
+pub fn binary(op: BinOp, lhs: i32, rhs: i32) -> Option<i32> {
+    match op {
+        BinOp::ADD => Some(lhs.wrapping_add(rhs)),
+        BinOp::SUB => Some(lhs.wrapping_sub(rhs)),
+        BinOp::MUL => Some(lhs.wrapping_mul(rhs)),
+        BinOp::DIV | BinOp::REM if rhs == 0 => None,
+        BinOp::DIV => Some(lhs.wrapping_div(rhs)),
+        BinOp::REM => Some(lhs.wrapping_rem(rhs)),
+        BinOp::EQ => Some((lhs == rhs) as i32),
+        BinOp::NE => Some((lhs != rhs) as i32),
+        BinOp::LT => Some((lhs < rhs) as i32),
+        BinOp::LE => Some((lhs <= rhs) as i32),
+        BinOp::GT => Some((lhs > rhs) as i32),
+        BinOp::GE => Some((lhs >= rhs) as i32),
+    }
+}
+
+pub fn checked_binary(op: BinOp, lhs: i32, rhs: i32) -> Option<i32> {
+    match op {
+        BinOp::ADD => lhs.checked_add(rhs),
+        BinOp::SUB => lhs.checked_sub(rhs),
+        BinOp::MUL => lhs.checked_mul(rhs),
+        BinOp::DIV => lhs.checked_div(rhs),
+        BinOp::REM => lhs.checked_rem(rhs),
+        _ => binary(op, lhs, rhs),
+    }
+}
+
+pub fn c_code_line(body: &[Stmt]) -> Option<usize> {
+    body.iter().find_map(|stmt| match &stmt.kind {
+        StmtKind::CCODE(_) => Some(stmt.line),
+        StmtKind::IF(_, then, otherwise) => c_code_line(then).or_else(|| c_code_line(otherwise)),
+        StmtKind::WHILE(_, body) => c_code_line(body),
+        _ => None,
+    })
+}
+
+pub fn divide_by_zero() -> Halt {
+    Halt::TRAP("attempt to divide by zero".to_string())
+}
+
+pub fn overflow(op: &str, file: &str, line: usize) -> Halt {
+    Halt::TRAP(format!(
+        "attempt to {} with overflow at {}:{}",
+        op, file, line
+    ))
+}
+
+pub fn out_of_bounds(len: usize, index: i32) -> Halt {
+    Halt::TRAP(format!(
+        "index out of bounds: the len is {} but the index is {}",
+        len, index
+    ))
+}
+
+pub fn includes_only(parts: &[CPart]) -> bool {
+    parts.iter().all(|part| match part {
+        CPart::TEXT(text) => text
+            .lines()
+            .all(|l| l.trim().is_empty() || l.trim().starts_with("#include")),
+        _ => false,
+    })
+}
+
+pub fn c_str(s: &[u8]) -> &[u8] {
+    match s.iter().position(|&b| b == 0) {
+        Some(end) => &s[..end],
+        None => s,
+    }
+}
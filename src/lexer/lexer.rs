@@ -1,10 +1,45 @@
-use crate::tokens::Tokens;
-use std::process;
+use crate::diagnostic::Diagnostic;
+use crate::tokens::{Span, Token, Tokens, Trivia};
+use std::fmt;
+use std::mem;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LexError {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ERROR[{}] on line {}: {}",
+            self.code, self.span.line, self.message
+        )
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(e: LexError) -> Self {
+        Diagnostic::new(e.span.line, e.message)
+            .with_code(e.code)
+            .with_span(e.span)
+    }
+}
+
+impl From<LexError> for Box<Diagnostic> {
+    fn from(e: LexError) -> Self {
+        Box::new(e.into())
+    }
+}
 
 pub struct Lexer {
     src: String,
     index: usize,
     pub line: usize,
+    trivia: Vec<Trivia>,
+    statement: bool,
 }
 
 impl Lexer {
@@ -13,38 +48,187 @@ impl Lexer {
             src: str,
             index: 0,
             line: 1,
+            trivia: Vec::new(),
+            statement: true,
         }
     }
 
-    pub fn next_token(&mut self) -> Tokens {
-        loop {
-            if self.index >= self.src.len() {
-                return Tokens::EOF;
-            }
-
-            let current_char = self.src[self.index..].chars().next().unwrap();
-            let char_len = current_char.len_utf8();
+    fn skip_whitespace(&mut self) {
+        let own_line = self.index == 0;
+        let mut newlines = 0;
 
+        while let Some(current_char) = self.src[self.index..].chars().next() {
             if current_char == '\n' {
                 self.line += 1;
+                newlines += 1;
+                if newlines == 2 {
+                    self.trivia.push(Trivia::BLANKLINE);
+                }
+            } else if self.src[self.index..].starts_with("//") {
+                let end = self.src[self.index..]
+                    .find('\n')
+                    .map_or(self.src.len(), |i| self.index + i);
+                let text = self.src[self.index..end].trim_end().to_string();
+                if own_line || newlines > 0 {
+                    self.trivia.push(Trivia::COMMENT(text));
+                } else {
+                    self.trivia.push(Trivia::TRAILING(text));
+                }
+                self.index = end;
+                newlines = 0;
+                continue;
             } else if !current_char.is_whitespace() {
                 break;
             }
 
-            self.index += char_len;
+            self.index += current_char.len_utf8();
+        }
+    }
+
+    pub fn take_trivia(&mut self) -> Vec<Trivia> {
+        mem::take(&mut self.trivia)
+    }
+
+    pub fn take_trailing(&mut self) -> Option<String> {
+        match self.trivia.first() {
+            Some(Trivia::TRAILING(_)) => match self.trivia.remove(0) {
+                Trivia::TRAILING(text) => Some(text),
+                _ => unreachable!(),
+            },
+            _ => None,
+        }
+    }
+
+    pub fn peek_char(&self) -> Option<char> {
+        self.src[self.index..].chars().find(|c| !c.is_whitespace())
+    }
+
+    fn span(&self, start: usize, line: usize) -> Span {
+        let line_start = self.src[..start].rfind('\n').map_or(0, |i| i + 1);
+        Span {
+            start,
+            end: self.index,
+            line,
+            column: self.src[line_start..start].chars().count() + 1,
+        }
+    }
+
+    fn error(
+        &self,
+        start: usize,
+        line: usize,
+        code: &'static str,
+        message: impl Into<String>,
+    ) -> LexError {
+        LexError {
+            code,
+            message: message.into(),
+            span: self.span(start, line),
+        }
+    }
+
+    pub fn raw_block(&mut self) -> Result<String, LexError> {
+        self.skip_whitespace();
+        let line = self.line;
+        self.index += 1;
+
+        let start = self.index;
+        let mut depth = 0;
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+
+        for c in self.src[start..].chars() {
+            if c == '\n' {
+                self.line += 1;
+            }
+
+            if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            } else if c == '"' || c == '\'' {
+                quote = Some(c);
+            } else if c == '{' {
+                depth += 1;
+            } else if c == '}' {
+                if depth == 0 {
+                    let block = self.src[start..self.index].to_string();
+                    self.index += c.len_utf8();
+                    return Ok(block);
+                }
+                depth -= 1;
+            }
+
+            self.index += c.len_utf8();
+        }
+
+        Err(self.error(start - 1, line, "E0005", "Unterminated c block"))
+    }
+
+    pub fn next_token(&mut self) -> Result<Token, LexError> {
+        self.skip_whitespace();
+        let (start, line) = (self.index, self.line);
+
+        match self.scan() {
+            Ok(kind) => Ok(Token {
+                kind,
+                span: self.span(start, line),
+                lexeme: self.src[start..self.index].to_string(),
+            }),
+            Err((code, message)) => Err(self.error(start, line, code, message)),
+        }
+    }
+
+    fn scan(&mut self) -> Result<Tokens, (&'static str, String)> {
+        if self.index >= self.src.len() {
+            return Ok(Tokens::EOF);
         }
 
-        let mut char = self.src[self.index..].chars().next().unwrap();
-        let mut char_len = char.len_utf8();
+        let char = self.src[self.index..].chars().next().unwrap();
+        let char_len = char.len_utf8();
 
-        match char {
+        let token = match char {
             ';' => {
                 self.index += char_len;
                 Tokens::SEMICOLON
             }
             '=' => {
                 self.index += char_len;
-                Tokens::EQUALS
+
+                if self.src[self.index..].starts_with('=') {
+                    self.index += char_len;
+                    Tokens::EQEQ
+                } else {
+                    Tokens::EQUALS
+                }
+            }
+            '!' if self.src[self.index + char_len..].starts_with('=') => {
+                self.index += 2;
+                Tokens::NOTEQ
+            }
+            '<' => {
+                self.index += char_len;
+
+                if self.src[self.index..].starts_with('=') {
+                    self.index += char_len;
+                    Tokens::LESSEQ
+                } else {
+                    Tokens::LESS
+                }
+            }
+            '>' => {
+                self.index += char_len;
+
+                if self.src[self.index..].starts_with('=') {
+                    self.index += char_len;
+                    Tokens::GREATEREQ
+                } else {
+                    Tokens::GREATER
+                }
             }
             '+' => {
                 self.index += char_len;
@@ -54,19 +238,44 @@ impl Lexer {
                 self.index += char_len;
                 Tokens::MINUS
             }
+            '*' => {
+                self.index += char_len;
+                Tokens::STAR
+            }
+            '/' => {
+                self.index += char_len;
+                Tokens::SLASH
+            }
+            '%' => {
+                self.index += char_len;
+                Tokens::PERCENT
+            }
             ':' => {
-                char_len = char.len_utf8();
                 self.index += char_len;
-                char = self.src[self.index..].chars().next().unwrap();
 
-                if char == ':' {
+                if self.src[self.index..].starts_with(':') {
                     self.index += char_len;
                     Tokens::DOUBLECOL
                 } else {
-                    eprintln!("ERROR on line {}: Unknown char: ':'", self.line);
-                    process::exit(1);
+                    Tokens::COLON
                 }
             }
+            '[' => {
+                self.index += char_len;
+                Tokens::OPENBRACKET
+            }
+            ']' => {
+                self.index += char_len;
+                Tokens::CLOSEBRACKET
+            }
+            ',' => {
+                self.index += char_len;
+                Tokens::COMMA
+            }
+            '#' => {
+                self.index += char_len;
+                Tokens::HASH
+            }
             '{' => {
                 self.index += char_len;
                 Tokens::OPENCURLY
@@ -85,27 +294,35 @@ impl Lexer {
             }
             '"' => {
                 self.index += char_len;
-                let mut identifier = String::new();
-                let mut id_iter = self.src[self.index..].chars();
+                let mut value = String::new();
+                let mut error = None;
 
-                while let Some(c) = id_iter.next() {
+                while let Some(c) = self.src[self.index..].chars().next() {
                     self.index += c.len_utf8();
-                    if c == '"' {
-                        return Tokens::STRING(identifier);
-                    } else {
-                        identifier.push(c);
+                    match c {
+                        '"' => return error.map_or(Ok(Tokens::STRING(value)), Err),
+                        '\\' => match self.escape() {
+                            Ok(c) => value.push(c),
+                            Err(e) => {
+                                error.get_or_insert(e);
+                            }
+                        },
+                        '\n' => {
+                            self.line += 1;
+                            value.push(c);
+                        }
+                        _ => value.push(c),
                     }
                 }
 
-                eprintln!("ERROR on line {}: Unterminated string literal", self.line);
-                process::exit(1);
+                return Err(("E0002", "Unterminated string literal".to_string()));
             }
-            _ if char.is_alphabetic() => {
+            _ if char.is_alphabetic() || char == '_' => {
                 let mut current_pos = self.index;
-                let mut id_iter = self.src[current_pos..].chars();
+                let id_iter = self.src[current_pos..].chars();
                 let mut identifier = String::new();
 
-                while let Some(c) = id_iter.next() {
+                for c in id_iter {
                     if c.is_alphanumeric() || c == '_' {
                         identifier.push(c);
                         current_pos += c.len_utf8();
@@ -120,17 +337,25 @@ impl Lexer {
                     "let" => Tokens::LET,
                     "void" => Tokens::VOID,
                     "int" => Tokens::INT,
+                    "str" => Tokens::STR,
                     "return" => Tokens::RETURN,
+                    "if" => Tokens::IF,
+                    "else" => Tokens::ELSE,
+                    "while" => Tokens::WHILE,
+                    "const" => Tokens::CONST,
+                    "import" => Tokens::IMPORT,
+                    "mod" => Tokens::MOD,
+                    "pub" => Tokens::PUB,
                     _ => Tokens::IDENT(identifier),
                 }
             }
 
             _ if char.is_numeric() => {
                 let mut current_pos = self.index;
-                let mut num_iter = self.src[current_pos..].chars();
+                let num_iter = self.src[current_pos..].chars();
                 let mut number_str = String::new();
 
-                while let Some(c) = num_iter.next() {
+                for c in num_iter {
                     if c.is_numeric() {
                         number_str.push(c);
                         current_pos += c.len_utf8();
@@ -143,18 +368,95 @@ impl Lexer {
                 match number_str.parse::<i32>() {
                     Ok(n) => Tokens::NUMBER(n),
                     Err(_) => {
-                        eprintln!(
-                            "ERROR on line {}: Invalid number format or overflow '{}'",
-                            self.line, number_str
-                        );
-                        process::exit(1);
+                        return Err((
+                            "E0003",
+                            format!("Invalid number format or overflow '{}'", number_str),
+                        ));
                     }
                 }
             }
             _ => {
-                eprintln!("ERROR on line {}: Unknown character '{}'", self.line, char);
-                process::exit(1);
+                self.index += char_len;
+                return Err(("E0001", format!("Unknown character '{}'", char)));
+            }
+        };
+        Ok(token)
+    }
+
+    fn escape(&mut self) -> Result<char, (&'static str, String)> {
+        let Some(c) = self.src[self.index..].chars().next() else {
+            return Err(("E0002", "Unterminated string literal".to_string()));
+        };
+        self.index += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+
+        match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' | '"' | '\'' => Ok(c),
+            'x' => {
+                let digits = self.src.get(self.index..self.index + 2).unwrap_or("");
+                match u8::from_str_radix(digits, 16) {
+                    Ok(byte) if byte < 0x80 && digits.chars().all(|d| d.is_ascii_hexdigit()) => {
+                        self.index += 2;
+                        Ok(byte as char)
+                    }
+                    _ => Err((
+                        "E0030",
+                        "Expected two hex digits between 00 and 7f after '\\x'".to_string(),
+                    )),
+                }
+            }
+            _ => Err((
+                "E0030",
+                format!("Unknown escape sequence '\\{}' in string literal", c),
+            )),
+        }
+    }
+}
+
+impl Iterator for Lexer {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = match self.next_token() {
+            Ok(Token {
+                kind: Tokens::IDENT(name),
+                span,
+                ..
+            }) if name == "c" && self.statement && self.peek_char() == Some('{') => {
+                self.raw_block().map(|code| Token {
+                    kind: Tokens::CBLOCK(code),
+                    span: Span {
+                        end: self.index,
+                        ..span
+                    },
+                    lexeme: self.src[span.start..self.index].to_string(),
+                })
             }
+            token => token,
+        };
+
+        self.statement = matches!(
+            token,
+            Ok(Token {
+                kind: Tokens::SEMICOLON
+                    | Tokens::OPENCURLY
+                    | Tokens::CLOSECURLY
+                    | Tokens::CLOSEBRACKET
+                    | Tokens::CBLOCK(_),
+                ..
+            })
+        );
+        match token {
+            Ok(Token {
+                kind: Tokens::EOF, ..
+            }) => None,
+            token => Some(token),
         }
     }
 }
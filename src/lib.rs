@@ -0,0 +1,164 @@
+#![allow(
+    clippy::upper_case_acronyms,
+    clippy::module_inception,
+    clippy::new_without_default
+)]
+
+pub mod analyzer;
+pub mod ast;
+pub mod bytecode;
+pub mod codegen;
+pub mod diagnostic;
+pub mod error_codes;
+pub mod formatter;
+pub mod interpreter;
+pub mod lexer;
+pub mod lints;
+pub mod lsp;
+pub mod optimizer;
+pub mod parser;
+pub mod symbol_table;
+pub mod tokens;
+
+pub use crate::diagnostic::Diagnostic;
+pub use crate::lexer::lexer::Lexer;
+pub use crate::parser::parser::Parser;
+
+use crate::analyzer::analyzer::Analyzer;
+use crate::ast::Program;
+use crate::bytecode::bytecode::Bytecode;
+use crate::bytecode::compiler::Compiler;
+use crate::codegen::asm::AsmCodegen;
+use crate::codegen::codegen::Codegen;
+use crate::codegen::llvm::LlvmCodegen;
+use crate::codegen::qbe::QbeCodegen;
+use crate::codegen::wasm::WasmCodegen;
+use crate::formatter::formatter::Formatter;
+use crate::lints::lints::{LintLevels, Linter};
+use crate::optimizer::optimizer::Optimizer;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    C,
+    ASM,
+    QBE,
+    LLVM,
+    WASM32,
+    BYTECODE,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::C => "c",
+            Format::ASM => "s",
+            Format::QBE => "ssa",
+            Format::LLVM => "ll",
+            Format::WASM32 => "wat",
+            Format::BYTECODE => "edbc",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub path: PathBuf,
+    pub output: Option<PathBuf>,
+    pub format: Format,
+    pub lib: bool,
+    pub line_info: bool,
+    pub opt_level: u8,
+    pub lints: LintLevels,
+}
+
+impl Options {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            output: None,
+            format: Format::C,
+            lib: false,
+            line_info: false,
+            opt_level: 0,
+            lints: LintLevels::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Output {
+    TEXT(String),
+    BYTECODE(Bytecode),
+}
+
+#[derive(Clone, Debug)]
+pub struct Compiled {
+    pub output: Output,
+    pub warnings: Vec<Diagnostic>,
+}
+
+pub fn compile(source: &str, options: &Options) -> Result<Compiled, Vec<Diagnostic>> {
+    let (mut program, mut warnings) = analyze(source, options).map_err(|e| vec![*e])?;
+    if warnings.iter().any(Diagnostic::is_error) {
+        return Err(warnings);
+    }
+
+    match generate(&mut program, options) {
+        Ok(output) => Ok(Compiled { output, warnings }),
+        Err(e) => {
+            warnings.push(*e);
+            Err(warnings)
+        }
+    }
+}
+
+pub fn format(source: &str, path: PathBuf) -> Result<String, Box<Diagnostic>> {
+    let module = Parser::new(source.to_string(), path).parse_syntax()?;
+    Ok(Formatter::new(source).format(&module))
+}
+
+fn analyze(source: &str, options: &Options) -> Result<(Program, Vec<Diagnostic>), Box<Diagnostic>> {
+    if options.lib && options.format == Format::BYTECODE {
+        return Err(Diagnostic::global(
+            "A library cannot be compiled to bytecode, bytecode needs a main function",
+        )
+        .into());
+    }
+
+    let mut program = Parser::new(source.to_string(), options.path.clone()).parse()?;
+    let mut analyzer = Analyzer::new(!options.lib);
+    analyzer.analyze(&mut program)?;
+    let warnings = Linter::new(&options.lints, analyzer.symbol_table()).lint(&program);
+    Ok((program, warnings))
+}
+
+fn generate(program: &mut Program, options: &Options) -> Result<Output, Box<Diagnostic>> {
+    Optimizer::new(options.opt_level).optimize(program)?;
+
+    let output = match &options.output {
+        Some(output) => output.clone(),
+        None => options.path.with_extension(options.format.extension()),
+    };
+    let code = match options.format {
+        Format::C => Codegen::new(
+            output.display().to_string(),
+            options.line_info,
+            options.opt_level == 0,
+        )
+        .generate(program),
+        Format::ASM => AsmCodegen::new(options.line_info, options.opt_level == 0).generate(program),
+        Format::QBE => QbeCodegen::new(options.opt_level == 0).generate(program),
+        Format::LLVM => {
+            LlvmCodegen::new(options.path.display().to_string(), options.opt_level == 0)
+                .generate(program)
+        }
+        Format::WASM32 => WasmCodegen::new(options.opt_level == 0).generate(program),
+        Format::BYTECODE => {
+            return Ok(Output::BYTECODE(
+                Compiler::new(options.opt_level == 0).compile(program)?,
+            ));
+        }
+    };
+    Ok(Output::TEXT(code?))
+}
@@ -0,0 +1,317 @@
+use crate::ast::{CPart, Expr, Function, ItemKind, Program, Stmt, StmtKind};
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::symbol_table::{ConstType, SymbolTable, qualify};
+use crate::tokens::Span;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+pub const LINTS: [(&str, &str); 5] = [
+    ("unused_variables", "W0001"),
+    ("unused_functions", "W0002"),
+    ("shadowed_bindings", "W0003"),
+    ("unreachable_code", "W0004"),
+    ("dead_c_comp_append", "W0005"),
+];
+
+pub fn is_lint(name: &str) -> bool {
+    name == "warnings" || LINTS.iter().any(|(lint, _)| *lint == name)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    ALLOW,
+    WARN,
+    DENY,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintLevels {
+    levels: HashMap<&'static str, Level>,
+}
+
+impl LintLevels {
+    pub fn new() -> Self {
+        Self {
+            levels: LINTS.iter().map(|(lint, _)| (*lint, Level::WARN)).collect(),
+        }
+    }
+
+    pub fn set(&mut self, name: &str, level: Level) -> Result<(), String> {
+        if !is_lint(name) {
+            return Err(format!("Unknown lint '{}'", name));
+        }
+        for (lint, current) in &mut self.levels {
+            if *lint == name || (name == "warnings" && *current != Level::ALLOW) {
+                *current = level;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn level(&self, lint: &str) -> Level {
+        self.levels[lint]
+    }
+}
+
+pub struct Linter<'a> {
+    levels: &'a LintLevels,
+    consts: HashMap<String, ConstType>,
+    symbols: &'a SymbolTable,
+    unread: HashSet<(String, usize)>,
+    module: String,
+    path: PathBuf,
+    allow: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(levels: &'a LintLevels, symbols: &'a SymbolTable) -> Self {
+        Self {
+            levels,
+            consts: HashMap::new(),
+            symbols,
+            unread: symbols
+                .bindings()
+                .iter()
+                .filter(|binding| binding.reads == 0 && !binding.name.starts_with('_'))
+                .map(|binding| (binding.module.clone(), binding.span.start))
+                .collect(),
+            module: String::new(),
+            path: PathBuf::new(),
+            allow: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn lint(mut self, program: &Program) -> Vec<Diagnostic> {
+        for module in &program.modules {
+            for item in &module.items {
+                if let ItemKind::CONST(constant) = &item.kind {
+                    self.consts
+                        .insert(constant.name.clone(), constant.value.clone());
+                }
+            }
+        }
+
+        for module in &program.modules {
+            self.module = module.name.clone();
+            self.path = module.path.clone();
+            let start = self.diagnostics.len();
+
+            for item in &module.items {
+                let depth = self.allow.len();
+                self.allow.extend(item.allow.iter().cloned());
+                match &item.kind {
+                    ItemKind::FUNCTION(func) => self.function(func, item.line),
+                    ItemKind::CONST(_) | ItemKind::IMPORT(_) => {}
+                    ItemKind::CCODE(parts) => self.c_code(parts, item.line),
+                }
+                self.allow.truncate(depth);
+            }
+
+            self.diagnostics[start..].sort_by_key(|diagnostic| diagnostic.line);
+        }
+
+        self.diagnostics
+    }
+
+    fn level(&self, lint: &str) -> Level {
+        if self
+            .allow
+            .iter()
+            .any(|name| name == lint || name == "warnings")
+        {
+            return Level::ALLOW;
+        }
+        self.levels.level(lint)
+    }
+
+    fn report(&mut self, lint: &str, level: Level, diagnostic: Diagnostic) {
+        let severity = match level {
+            Level::ALLOW => return,
+            Level::WARN => Severity::WARNING,
+            Level::DENY => Severity::ERROR,
+        };
+        let code = LINTS.iter().find(|(name, _)| *name == lint).unwrap().1;
+        self.diagnostics.push(
+            diagnostic
+                .with_code(code)
+                .with_severity(severity)
+                .with_file(&self.path),
+        );
+    }
+
+    fn function(&mut self, func: &Function, line: usize) {
+        let name = func.name.rsplit("::").next().unwrap();
+        if func.name != "main"
+            && !func.public
+            && !name.starts_with('_')
+            && self.symbols.func_calls(&func.name) == 0
+        {
+            let level = self.level("unused_functions");
+            self.report(
+                "unused_functions",
+                level,
+                Diagnostic::new(line, format!("Function '{}' is never called", func.name))
+                    .with_span(func.span)
+                    .suggest(underscore(name)),
+            );
+        }
+
+        for ((param, _), span) in func.params.iter().zip(&func.param_spans) {
+            self.shadowing(param, line);
+            self.unused(param, *span, line, "Parameter '{}' is never used");
+        }
+        self.block(&func.body, true);
+    }
+
+    fn unused(&mut self, name: &str, span: Span, line: usize, message: &str) {
+        if !self.unread.contains(&(self.module.clone(), span.start)) {
+            return;
+        }
+        let level = self.level("unused_variables");
+        self.report(
+            "unused_variables",
+            level,
+            Diagnostic::new(line, message.replace("{}", name))
+                .with_span(span)
+                .suggest(underscore(name)),
+        );
+    }
+
+    fn shadowing(&mut self, name: &str, line: usize) {
+        let constant = qualify(&self.module, name);
+        if self.consts.contains_key(&constant) {
+            let level = self.level("shadowed_bindings");
+            self.report(
+                "shadowed_bindings",
+                level,
+                Diagnostic::new(
+                    line,
+                    format!("Variable '{}' shadows the constant '{}'", name, constant),
+                ),
+            );
+        }
+    }
+
+    fn block(&mut self, body: &[Stmt], tail: bool) {
+        for (i, stmt) in body.iter().enumerate() {
+            self.stmt(stmt);
+
+            let rest = &body[i + 1..];
+            if rest.is_empty() || !self.diverges(stmt) {
+                continue;
+            }
+
+            let mandatory_return =
+                tail && rest.len() == 1 && matches!(rest[0].kind, StmtKind::RETURN(_));
+            if !mandatory_return {
+                self.unreachable(
+                    &rest[0],
+                    stmt.line,
+                    "Any code following this statement is unreachable",
+                );
+            }
+            for stmt in rest {
+                self.stmt(stmt);
+            }
+            break;
+        }
+    }
+
+    fn unreachable(&mut self, stmt: &Stmt, cause: usize, note: &str) {
+        let depth = self.allow.len();
+        self.allow.extend(stmt.allow.iter().cloned());
+        let level = self.level("unreachable_code");
+        self.allow.truncate(depth);
+
+        self.report(
+            "unreachable_code",
+            level,
+            Diagnostic::new(stmt.line, "Unreachable statement").note(cause, note),
+        );
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        let depth = self.allow.len();
+        self.allow.extend(stmt.allow.iter().cloned());
+
+        match &stmt.kind {
+            StmtKind::LET(name, _, _) => {
+                self.shadowing(name, stmt.line);
+                self.unused(name, stmt.span, stmt.line, "Variable '{}' is never read");
+            }
+            StmtKind::ASSIGN(..) | StmtKind::EXPR(_) | StmtKind::RETURN(_) => {}
+            StmtKind::IF(cond, then, otherwise) => {
+                match self.const_int(cond) {
+                    Some(0) if !then.is_empty() => {
+                        self.unreachable(&then[0], stmt.line, "This condition is always false")
+                    }
+                    Some(n) if n != 0 && !otherwise.is_empty() => {
+                        self.unreachable(&otherwise[0], stmt.line, "This condition is always true")
+                    }
+                    _ => {}
+                }
+                self.block(then, false);
+                self.block(otherwise, false);
+            }
+            StmtKind::WHILE(cond, body) => {
+                if self.const_int(cond) == Some(0) && !body.is_empty() {
+                    self.unreachable(&body[0], stmt.line, "This condition is always false");
+                }
+                self.block(body, false);
+            }
+            StmtKind::CCODE(parts) => self.c_code(parts, stmt.line),
+        }
+
+        self.allow.truncate(depth);
+    }
+
+    fn c_code(&mut self, parts: &[CPart], line: usize) {
+        let empty = parts.iter().all(|part| match part {
+            CPart::TEXT(text) => text.trim().is_empty(),
+            CPart::IDENT(_) | CPart::CONST(_) | CPart::ITEM(_) => false,
+        });
+        if empty {
+            let level = self.level("dead_c_comp_append");
+            self.report(
+                "dead_c_comp_append",
+                level,
+                Diagnostic::new(line, "This c code is empty and adds nothing to the output"),
+            );
+        }
+    }
+
+    fn const_int(&self, expr: &Expr) -> Option<i32> {
+        match expr {
+            Expr::NUMBER(n) => Some(*n),
+            Expr::CONST(name) => match self.consts.get(name) {
+                Some(ConstType::INT32(n)) => Some(*n),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn diverges(&self, stmt: &Stmt) -> bool {
+        match &stmt.kind {
+            StmtKind::RETURN(_) => true,
+            StmtKind::EXPR(Expr::BUILTIN(name, _)) => name == "exit",
+            StmtKind::IF(cond, then, otherwise) => match self.const_int(cond) {
+                Some(0) => self.block_diverges(otherwise),
+                Some(_) => self.block_diverges(then),
+                None => self.block_diverges(then) && self.block_diverges(otherwise),
+            },
+            StmtKind::WHILE(cond, _) => self.const_int(cond).is_some_and(|n| n != 0),
+            _ => false,
+        }
+    }
+
+    fn block_diverges(&self, body: &[Stmt]) -> bool {
+        body.iter().any(|stmt| self.diverges(stmt))
+    }
+}
+
+fn underscore(name: &str) -> String {
+    format!("Prefix the name with an underscore: '_{}'", name)
+}
@@ -0,0 +1,866 @@
+use crate::analyzer::analyzer::Analyzer;
+use crate::ast::{ItemKind, Program};
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::lints::lints::{LintLevels, Linter};
+use crate::lsp::json::Json;
+use crate::parser::parser::Parser;
+use crate::symbol_table::{Binding, SymbolTable, params_to_string};
+use crate::tokens::Span;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+type RpcError = (i64, String);
+
+struct Analysis {
+    program: Program,
+    analyzer: Analyzer,
+}
+
+enum Symbol<'a> {
+    VAR(&'a Binding),
+    FUNC(String),
+    CONST(String),
+}
+
+struct Document {
+    uri: String,
+    path: PathBuf,
+    text: String,
+    analysis: Option<Analysis>,
+    published: Vec<String>,
+}
+
+impl Document {
+    fn check(&mut self) -> Vec<Diagnostic> {
+        let mut program = match Parser::new(self.text.clone(), self.path.clone()).parse() {
+            Ok(program) => program,
+            Err(e) => {
+                self.analysis = None;
+                return vec![*e];
+            }
+        };
+        let mut analyzer = Analyzer::new(false);
+        let diagnostics = match analyzer.analyze(&mut program) {
+            Ok(()) => Linter::new(&LintLevels::new(), analyzer.symbol_table()).lint(&program),
+            Err(e) => vec![*e],
+        };
+        self.analysis = Some(Analysis { program, analyzer });
+        diagnostics
+    }
+
+    fn symbols(&self) -> Option<&SymbolTable> {
+        self.analysis
+            .as_ref()
+            .map(|analysis| analysis.analyzer.symbol_table())
+    }
+
+    fn function_start(&self, offset: usize) -> Option<usize> {
+        let root = self.analysis.as_ref()?.program.modules.last()?;
+        let item = root
+            .items
+            .iter()
+            .filter(|item| item.span.start <= offset)
+            .max_by_key(|item| item.span.start)?;
+        match item.kind {
+            ItemKind::FUNCTION(_) => Some(item.span.start),
+            _ => None,
+        }
+    }
+
+    fn resolve(&self, offset: usize) -> Option<(Symbol<'_>, usize, usize)> {
+        let symbols = self.symbols()?;
+        let (start, end) = word_at(&self.text, offset)?;
+        let word = &self.text[start..end];
+
+        if !word.contains("::") {
+            let declared = symbols
+                .bindings()
+                .iter()
+                .find(|binding| binding.module.is_empty() && binding.span.start == start);
+            let visible = || {
+                let from = self.function_start(offset)?;
+                symbols
+                    .scope_at("", from, offset)
+                    .into_iter()
+                    .find(|binding| binding.name == word)
+            };
+            if let Some(binding) = declared.or_else(visible) {
+                return Some((Symbol::VAR(binding), start, end));
+            }
+        }
+
+        if symbols.get_func(word).is_ok() {
+            Some((Symbol::FUNC(word.to_string()), start, end))
+        } else if symbols.get_const(word).is_ok() {
+            Some((Symbol::CONST(word.to_string()), start, end))
+        } else {
+            None
+        }
+    }
+
+    fn item_location(&self, name: &str) -> Option<Json> {
+        let (module_name, base) = name.rsplit_once("::").unwrap_or(("", name));
+        let program = &self.analysis.as_ref()?.program;
+        let module = program.modules.iter().find(|m| m.name == module_name)?;
+        let span = module.items.iter().find_map(|item| match &item.kind {
+            ItemKind::FUNCTION(func) if func.name.rsplit("::").next() == Some(base) => {
+                Some(func.span)
+            }
+            ItemKind::CONST(constant) if constant.name.rsplit("::").next() == Some(base) => {
+                Some(item.span)
+            }
+            _ => None,
+        })?;
+
+        if module.name.is_empty() {
+            return Some(location(&self.uri, span_range(&self.text, span)));
+        }
+        let text = fs::read_to_string(&module.path).ok()?;
+        let path = fs::canonicalize(&module.path).unwrap_or_else(|_| module.path.clone());
+        Some(location(&path_to_uri(&path), span_range(&text, span)))
+    }
+}
+
+pub struct Server {
+    documents: HashMap<String, Document>,
+    outgoing: Vec<Json>,
+    shutdown: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self {
+            documents: HashMap::new(),
+            outgoing: Vec::new(),
+            shutdown: false,
+        }
+    }
+
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> i32 {
+        while let Some(body) = read_message(&mut input) {
+            match Json::parse(&body) {
+                Ok(message) => {
+                    if message.get("method").and_then(Json::as_str) == Some("exit") {
+                        return if self.shutdown { 0 } else { 1 };
+                    }
+                    self.handle(&message);
+                }
+                Err(e) => self.respond(Json::NULL, Err((PARSE_ERROR, e))),
+            }
+
+            for message in self.outgoing.drain(..) {
+                if write_message(&mut output, &message).is_err() {
+                    return 1;
+                }
+            }
+        }
+        1
+    }
+
+    fn handle(&mut self, message: &Json) {
+        let Some(method) = message.get("method").and_then(Json::as_str) else {
+            return;
+        };
+        let params = message.get("params").unwrap_or(&Json::NULL);
+        let Some(id) = message.get("id") else {
+            self.notification(method, params);
+            return;
+        };
+
+        let result = if self.shutdown {
+            Err((INVALID_REQUEST, "The server is shutting down".to_string()))
+        } else {
+            self.request(method, params)
+        };
+        self.respond(id.clone(), result);
+    }
+
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, RpcError> {
+        match method {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::NULL)
+            }
+            "textDocument/definition" => {
+                let (document, offset) = self.position(params)?;
+                Ok(definition(document, offset))
+            }
+            "textDocument/hover" => {
+                let (document, offset) = self.position(params)?;
+                Ok(hover(document, offset))
+            }
+            "textDocument/documentSymbol" => Ok(document_symbols(self.document(params)?)),
+            "textDocument/completion" => {
+                let (document, offset) = self.position(params)?;
+                Ok(completion(document, offset))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method '{}'", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) {
+        let document = params.get("textDocument");
+        let Some(uri) = document.and_then(|d| d.get("uri")).and_then(Json::as_str) else {
+            return;
+        };
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = document.and_then(|d| d.get("text")).and_then(Json::as_str);
+                self.update(uri, text.unwrap_or_default());
+            }
+            "textDocument/didChange" => {
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                if let Some(text) = text {
+                    self.update(uri, text);
+                }
+            }
+            "textDocument/didClose" => {
+                if let Some(document) = self.documents.remove(uri) {
+                    for uri in document.published {
+                        self.outgoing.push(publish(&uri, Vec::new()));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: &str) {
+        let document = self
+            .documents
+            .entry(uri.to_string())
+            .or_insert_with(|| Document {
+                uri: uri.to_string(),
+                path: uri_to_path(uri),
+                text: String::new(),
+                analysis: None,
+                published: Vec::new(),
+            });
+        document.text = text.to_string();
+        let diagnostics = document.check();
+
+        let mut files: Vec<(String, Vec<Json>)> = vec![(uri.to_string(), Vec::new())];
+        for diagnostic in &diagnostics {
+            let (target, json) = match &diagnostic.file {
+                Some(path) if *path != document.path => {
+                    let text = fs::read_to_string(path).unwrap_or_default();
+                    let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+                    let target = path_to_uri(&path);
+                    let json = diagnostic_json(&target, &text, diagnostic);
+                    (target, json)
+                }
+                _ => (
+                    uri.to_string(),
+                    diagnostic_json(uri, &document.text, diagnostic),
+                ),
+            };
+            match files.iter_mut().find(|(uri, _)| *uri == target) {
+                Some((_, list)) => list.push(json),
+                None => files.push((target, vec![json])),
+            }
+        }
+
+        let published = files.iter().map(|(uri, _)| uri.clone()).collect();
+        for stale in mem::replace(&mut document.published, published) {
+            if !files.iter().any(|(uri, _)| *uri == stale) {
+                self.outgoing.push(publish(&stale, Vec::new()));
+            }
+        }
+        for (uri, list) in files {
+            self.outgoing.push(publish(&uri, list));
+        }
+    }
+
+    fn document(&self, params: &Json) -> Result<&Document, RpcError> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|d| d.get("uri"))
+            .and_then(Json::as_str)
+            .ok_or_else(|| (INVALID_PARAMS, "Missing textDocument.uri".to_string()))?;
+        self.documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("Document '{}' is not open", uri)))
+    }
+
+    fn position(&self, params: &Json) -> Result<(&Document, usize), RpcError> {
+        let document = self.document(params)?;
+        let position = params.get("position");
+        let line = position
+            .and_then(|p| p.get("line"))
+            .and_then(Json::as_usize);
+        let character = position
+            .and_then(|p| p.get("character"))
+            .and_then(Json::as_usize);
+        match (line, character) {
+            (Some(line), Some(character)) => {
+                Ok((document, offset_at(&document.text, line, character)))
+            }
+            _ => Err((INVALID_PARAMS, "Missing or invalid position".to_string())),
+        }
+    }
+
+    fn respond(&mut self, id: Json, result: Result<Json, RpcError>) {
+        let outcome = match result {
+            Ok(result) => ("result", result),
+            Err((code, message)) => (
+                "error",
+                Json::object(vec![
+                    ("code", Json::NUMBER(code as f64)),
+                    ("message", message.into()),
+                ]),
+            ),
+        };
+        self.outgoing.push(Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("id", id),
+            outcome,
+        ]));
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object(vec![
+        (
+            "capabilities",
+            Json::object(vec![
+                ("textDocumentSync", 1.into()),
+                ("hoverProvider", true.into()),
+                ("definitionProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+                (
+                    "completionProvider",
+                    Json::object(vec![("triggerCharacters", vec![":".into()].into())]),
+                ),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object(vec![
+                ("name", "edge".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+fn definition(document: &Document, offset: usize) -> Json {
+    match document.resolve(offset) {
+        Some((Symbol::VAR(binding), _, _)) => {
+            location(&document.uri, span_range(&document.text, binding.span))
+        }
+        Some((Symbol::FUNC(name) | Symbol::CONST(name), _, _)) => {
+            document.item_location(&name).unwrap_or(Json::NULL)
+        }
+        None => Json::NULL,
+    }
+}
+
+fn hover(document: &Document, offset: usize) -> Json {
+    let (Some((symbol, start, end)), Some(symbols)) =
+        (document.resolve(offset), document.symbols())
+    else {
+        return Json::NULL;
+    };
+    let text = match symbol {
+        Symbol::VAR(binding) if binding.param => format!("{}: {}", binding.name, binding.var_type),
+        Symbol::VAR(binding) => format!("let {}: {}", binding.name, binding.var_type),
+        Symbol::FUNC(name) => signature(symbols, &name),
+        Symbol::CONST(name) => const_signature(symbols, &name),
+    };
+
+    Json::object(vec![
+        (
+            "contents",
+            Json::object(vec![
+                ("kind", "markdown".into()),
+                ("value", format!("```edge\n{}\n```", text).into()),
+            ]),
+        ),
+        ("range", range(&document.text, start, end)),
+    ])
+}
+
+fn document_symbols(document: &Document) -> Json {
+    let Some(analysis) = &document.analysis else {
+        return Json::ARRAY(Vec::new());
+    };
+    let symbols = analysis.analyzer.symbol_table();
+    let Some(root) = analysis.program.modules.last() else {
+        return Json::ARRAY(Vec::new());
+    };
+
+    let entries = root
+        .items
+        .iter()
+        .filter_map(|item| match &item.kind {
+            ItemKind::FUNCTION(func) => {
+                Some((&func.name, 12, func.span, signature(symbols, &func.name)))
+            }
+            ItemKind::CONST(constant) => Some((
+                &constant.name,
+                14,
+                item.span,
+                const_signature(symbols, &constant.name),
+            )),
+            ItemKind::CCODE(_) | ItemKind::IMPORT(_) => None,
+        })
+        .map(|(name, kind, span, detail)| {
+            let range = span_range(&document.text, span);
+            Json::object(vec![
+                ("name", name.as_str().into()),
+                ("detail", detail.into()),
+                ("kind", kind.into()),
+                ("range", range.clone()),
+                ("selectionRange", range),
+            ])
+        })
+        .collect();
+    Json::ARRAY(entries)
+}
+
+fn completion(document: &Document, offset: usize) -> Json {
+    let Some(symbols) = document.symbols() else {
+        return Json::ARRAY(Vec::new());
+    };
+    let item = |label: &str, kind: usize, detail: String| {
+        Json::object(vec![
+            ("label", label.into()),
+            ("kind", kind.into()),
+            ("detail", detail.into()),
+        ])
+    };
+    let visible = |name: &str| match name.split_once("::") {
+        Some((module, _)) => symbols.has_import("", module) && symbols.is_public(name),
+        None => true,
+    };
+
+    let mut items = Vec::new();
+    if let Some(from) = document.function_start(offset) {
+        for binding in symbols.scope_at("", from, offset) {
+            items.push(item(&binding.name, 6, binding.var_type.to_string()));
+        }
+    }
+    for name in symbols.functions().into_iter().filter(|name| visible(name)) {
+        items.push(item(name, 3, signature(symbols, name)));
+    }
+    for name in symbols.consts().into_iter().filter(|name| visible(name)) {
+        items.push(item(name, 21, const_signature(symbols, name)));
+    }
+    Json::ARRAY(items)
+}
+
+fn signature(symbols: &SymbolTable, name: &str) -> String {
+    let ret_type = match symbols.get_func(name) {
+        Ok(ret_type) => ret_type.to_string(),
+        Err(_) => return name.to_string(),
+    };
+    format!(
+        "{}{} :: {}({})",
+        if symbols.is_public(name) { "pub " } else { "" },
+        name,
+        ret_type,
+        params_to_string(&symbols.get_func_params(name))
+    )
+}
+
+fn const_signature(symbols: &SymbolTable, name: &str) -> String {
+    match symbols.get_const(name) {
+        Ok(value) => format!(
+            "{}const {} = {}",
+            if symbols.is_public(name) { "pub " } else { "" },
+            name,
+            value
+        ),
+        Err(_) => name.to_string(),
+    }
+}
+
+fn diagnostic_json(uri: &str, text: &str, diagnostic: &Diagnostic) -> Json {
+    let range = match (diagnostic.span, diagnostic.line) {
+        (Some(span), _) => span_range(text, span),
+        (None, Some(line)) => line_range(text, line),
+        (None, None) => line_range(text, 1),
+    };
+    let severity: usize = match diagnostic.severity {
+        Severity::ERROR => 1,
+        Severity::WARNING => 2,
+    };
+
+    let mut message = diagnostic.message.clone();
+    for suggestion in &diagnostic.suggestions {
+        message.push_str(&format!("\nHELP: {}", suggestion));
+    }
+    let related = diagnostic
+        .notes
+        .iter()
+        .map(|note| {
+            Json::object(vec![
+                ("location", location(uri, line_range(text, note.line))),
+                ("message", note.message.as_str().into()),
+            ])
+        })
+        .collect();
+
+    let mut fields = vec![
+        ("range", range),
+        ("severity", severity.into()),
+        ("source", "edge".into()),
+        ("message", message.into()),
+        ("relatedInformation", Json::ARRAY(related)),
+    ];
+    if let Some(code) = diagnostic.code {
+        fields.push(("code", code.into()));
+    }
+    Json::object(fields)
+}
+
+fn publish(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Json::object(vec![
+                ("uri", uri.into()),
+                ("diagnostics", Json::ARRAY(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+fn location(uri: &str, range: Json) -> Json {
+    Json::object(vec![("uri", uri.into()), ("range", range)])
+}
+
+fn word_at(text: &str, offset: usize) -> Option<(usize, usize)> {
+    let bytes = text.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+
+    let mut start = offset.min(bytes.len());
+    loop {
+        if start > 0 && is_ident(bytes[start - 1]) {
+            start -= 1;
+        } else if start > 2 && &bytes[start - 2..start] == b"::" && is_ident(bytes[start - 3]) {
+            start -= 2;
+        } else {
+            break;
+        }
+    }
+
+    let mut end = offset.min(bytes.len());
+    loop {
+        if end < bytes.len() && is_ident(bytes[end]) {
+            end += 1;
+        } else if end + 2 < bytes.len() && &bytes[end..end + 2] == b"::" && is_ident(bytes[end + 2])
+        {
+            end += 2;
+        } else {
+            break;
+        }
+    }
+
+    if start == end || bytes[start].is_ascii_digit() {
+        return None;
+    }
+    Some((start, end))
+}
+
+fn offset_at(text: &str, line: usize, character: usize) -> usize {
+    let mut start = 0;
+    for _ in 0..line {
+        match text[start..].find('\n') {
+            Some(i) => start += i + 1,
+            None => return text.len(),
+        }
+    }
+
+    let mut units = 0;
+    for (i, c) in text[start..].char_indices() {
+        if c == '\n' || units >= character {
+            return start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+fn position(text: &str, offset: usize) -> Json {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    Json::object(vec![
+        ("line", before.matches('\n').count().into()),
+        ("character", character.into()),
+    ])
+}
+
+fn range(text: &str, start: usize, end: usize) -> Json {
+    Json::object(vec![
+        ("start", position(text, start)),
+        ("end", position(text, end)),
+    ])
+}
+
+fn span_range(text: &str, span: Span) -> Json {
+    range(text, span.start, span.end)
+}
+
+fn line_range(text: &str, line: usize) -> Json {
+    let line = line.saturating_sub(1);
+    range(
+        text,
+        offset_at(text, line, 0),
+        offset_at(text, line, usize::MAX),
+    )
+}
+
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = path
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.display().to_string().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+fn read_message(input: &mut impl BufRead) -> Option<String> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; length?];
+    input.read_exact(&mut body).ok()?;
+    Some(String::from_utf8_lossy(&body).into_owned())
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const URI: &str = "file:///tmp/lsp_test.ed";
+    const SOURCE: &str = "add :: int(a: int, b: int) {\n    return a + b;\n}\n\nmain :: int {\n    let unused = 1;\n    let x = add(1, 2);\n    return x;\n}\n";
+
+    fn request(id: usize, method: &str, params: Json) -> Json {
+        Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("id", id.into()),
+            ("method", method.into()),
+            ("params", params),
+        ])
+    }
+
+    fn notification(method: &str, params: Json) -> Json {
+        Json::object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("method", method.into()),
+            ("params", params),
+        ])
+    }
+
+    fn at(line: usize, character: usize) -> Json {
+        Json::object(vec![
+            ("textDocument", Json::object(vec![("uri", URI.into())])),
+            (
+                "position",
+                Json::object(vec![("line", line.into()), ("character", character.into())]),
+            ),
+        ])
+    }
+
+    fn change(text: &str) -> Json {
+        Json::object(vec![
+            ("textDocument", Json::object(vec![("uri", URI.into())])),
+            (
+                "contentChanges",
+                vec![Json::object(vec![("text", text.into())])].into(),
+            ),
+        ])
+    }
+
+    fn exchange(messages: &[Json]) -> (i32, Vec<Json>) {
+        let mut input = Vec::new();
+        for message in messages {
+            write_message(&mut input, message).unwrap();
+        }
+        let mut output = Vec::new();
+        let code = Server::new().run(Cursor::new(input), &mut output);
+
+        let mut replies = Vec::new();
+        let mut output = Cursor::new(output);
+        while let Some(body) = read_message(&mut output) {
+            replies.push(Json::parse(&body).unwrap());
+        }
+        (code, replies)
+    }
+
+    fn reply(replies: &[Json], id: usize) -> &Json {
+        replies
+            .iter()
+            .find(|reply| reply.get("id") == Some(&Json::from(id)))
+            .and_then(|reply| reply.get("result"))
+            .unwrap()
+    }
+
+    fn labels(values: &Json, key: &str) -> Vec<String> {
+        values
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|value| value.get(key).and_then(Json::as_str))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn session() {
+        let open = Json::object(vec![(
+            "textDocument",
+            Json::object(vec![
+                ("uri", URI.into()),
+                ("languageId", "edge".into()),
+                ("version", 1.into()),
+                ("text", SOURCE.into()),
+            ]),
+        )]);
+        let (code, replies) = exchange(&[
+            request(1, "initialize", Json::object(vec![])),
+            notification("initialized", Json::object(vec![])),
+            notification("textDocument/didOpen", open),
+            request(2, "textDocument/hover", at(6, 12)),
+            request(3, "textDocument/definition", at(7, 11)),
+            request(4, "textDocument/documentSymbol", at(0, 0)),
+            request(5, "textDocument/completion", at(7, 4)),
+            notification(
+                "textDocument/didChange",
+                change(SOURCE.trim_end().trim_end_matches('}')),
+            ),
+            request(6, "textDocument/hover", at(6, 12)),
+            request(7, "shutdown", Json::NULL),
+            notification("exit", Json::NULL),
+        ]);
+        assert_eq!(code, 0);
+
+        let capabilities = reply(&replies, 1).get("capabilities").unwrap();
+        assert_eq!(capabilities.get("hoverProvider"), Some(&Json::BOOL(true)));
+
+        let published: Vec<&Json> = replies
+            .iter()
+            .filter(|reply| {
+                reply.get("method").and_then(Json::as_str)
+                    == Some("textDocument/publishDiagnostics")
+            })
+            .map(|reply| reply.get("params").unwrap())
+            .collect();
+        assert_eq!(published.len(), 2);
+        assert_eq!(published[0].get("uri").and_then(Json::as_str), Some(URI));
+        let diagnostics = published[0].get("diagnostics").unwrap().as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0]
+                .get("message")
+                .and_then(Json::as_str)
+                .unwrap()
+                .contains("unused")
+        );
+        let start = diagnostics[0].get("range").unwrap().get("start").unwrap();
+        assert_eq!(start.get("line").and_then(Json::as_usize), Some(5));
+        assert_eq!(
+            published[1]
+                .get("diagnostics")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let hover = reply(&replies, 2).get("contents").unwrap();
+        assert_eq!(
+            hover.get("value").and_then(Json::as_str),
+            Some("```edge\nadd :: int(a: int, b: int)\n```")
+        );
+
+        let definition = reply(&replies, 3);
+        assert_eq!(definition.get("uri").and_then(Json::as_str), Some(URI));
+        let start = definition.get("range").unwrap().get("start").unwrap();
+        assert_eq!(start.get("line").and_then(Json::as_usize), Some(6));
+        assert_eq!(start.get("character").and_then(Json::as_usize), Some(8));
+
+        assert_eq!(labels(reply(&replies, 4), "name"), ["add", "main"]);
+
+        let completion = labels(reply(&replies, 5), "label");
+        for label in ["unused", "x", "add", "main"] {
+            assert!(completion.iter().any(|l| l == label), "missing {}", label);
+        }
+        assert!(!completion.iter().any(|l| l == "a"));
+
+        assert_eq!(reply(&replies, 6), &Json::NULL);
+        assert_eq!(reply(&replies, 7), &Json::NULL);
+    }
+}
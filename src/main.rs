@@ -1,31 +1,259 @@
-mod codegen;
+#![allow(clippy::upper_case_acronyms)]
+
 mod compiler_args;
-mod lexer;
-mod parser;
-mod symbol_table;
-mod tokens;
-use crate::compiler_args::CompilerArgs;
-use crate::parser::parser::Parser;
-use clap::Parser as ClapParser;
+use crate::compiler_args::{Backend, Command, CompilerArgs, Emit, ErrorFormat, Target};
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use edge::analyzer::analyzer::Analyzer;
+use edge::bytecode::bytecode::Bytecode;
+use edge::bytecode::vm::Vm;
+use edge::error_codes;
+use edge::interpreter::interpreter::{Halt, Interpreter};
+use edge::lints::lints::{Level, LintLevels, Linter};
+use edge::lsp::server::Server;
+use edge::{Diagnostic, Format, Lexer, Options, Output, Parser};
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
 use std::process;
 use std::time::Instant;
 
-fn main() {
-    let args = CompilerArgs::parse();
+fn check_extension(source_path: &Path, format: ErrorFormat) {
+    if source_path.extension().is_none_or(|ext| ext != "ed") {
+        report(
+            vec![Diagnostic::global(format!(
+                "Source file must end with the '.ed' extension. Found: {}",
+                source_path.display()
+            ))],
+            format,
+        );
+    }
+}
 
-    let source_path = args.input;
+fn read_source(source_path: &Path, format: ErrorFormat) -> String {
+    fs::read_to_string(source_path).unwrap_or_else(|e| {
+        report(
+            vec![
+                Diagnostic::global(format!(
+                    "Cannot read source file {}: {}",
+                    source_path.display(),
+                    e
+                ))
+                .with_file(source_path),
+            ],
+            format,
+        )
+    })
+}
 
-    if source_path.extension().map_or(true, |ext| ext != "ed") {
-        eprintln!(
-            "ERROR: Source file must end with the '.ed' extension. Found: {}",
-            source_path.display()
-        );
+fn emit(diagnostic: &Diagnostic, format: ErrorFormat) {
+    match format {
+        ErrorFormat::HUMAN => eprintln!("{}", diagnostic),
+        ErrorFormat::JSON => eprintln!("{}", diagnostic.to_json()),
+    }
+}
+
+fn report(diagnostics: Vec<Diagnostic>, format: ErrorFormat) -> ! {
+    for diagnostic in &diagnostics {
+        emit(diagnostic, format);
+    }
+    process::exit(1);
+}
+
+fn fail(message: &str, format: ErrorFormat) -> ! {
+    report(vec![Diagnostic::global(message)], format);
+}
+
+fn lint_levels(matches: &ArgMatches, format: ErrorFormat) -> LintLevels {
+    let mut flags = Vec::new();
+    for (id, level) in [
+        ("warn", Level::WARN),
+        ("allow", Level::ALLOW),
+        ("deny", Level::DENY),
+    ] {
+        if let (Some(indices), Some(names)) =
+            (matches.indices_of(id), matches.get_many::<String>(id))
+        {
+            flags.extend(indices.zip(names).map(|(index, name)| (index, name, level)));
+        }
+    }
+    flags.sort_by_key(|(index, _, _)| *index);
+
+    let mut lints = LintLevels::new();
+    for (_, name, level) in flags {
+        if let Err(e) = lints.set(name, level) {
+            report(vec![Diagnostic::global(e).with_code("E0028")], format);
+        }
+    }
+    lints
+}
+
+fn run(source_path: PathBuf, program_args: Vec<String>) {
+    let mut args = vec![source_path.display().to_string()];
+    args.extend(program_args);
+
+    if source_path.extension().is_some_and(|ext| ext == "edbc") {
+        let bytecode = fs::read(&source_path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| Bytecode::deserialize(&data));
+        let bytecode = match bytecode {
+            Ok(bytecode) => bytecode,
+            Err(e) => {
+                eprintln!("ERROR: Cannot load {}: {}", source_path.display(), e);
+                process::exit(1);
+            }
+        };
+        match Vm::new(&bytecode, args, BufWriter::new(io::stdout())).run() {
+            Ok(result) => halt(result),
+            Err(e) => {
+                eprintln!(
+                    "ERROR: Corrupt bytecode in {}: {}",
+                    source_path.display(),
+                    e
+                );
+                process::exit(1);
+            }
+        }
+    }
+
+    check_extension(&source_path, ErrorFormat::HUMAN);
+    let source_code = read_source(&source_path, ErrorFormat::HUMAN);
+
+    let mut program = Parser::new(source_code, source_path.clone())
+        .parse()
+        .unwrap_or_else(|e| report(vec![*e], ErrorFormat::HUMAN));
+    let mut analyzer = Analyzer::new(true);
+    if let Err(e) = analyzer.analyze(&mut program) {
+        report(vec![*e], ErrorFormat::HUMAN);
+    }
+    let diagnostics = Linter::new(&LintLevels::new(), analyzer.symbol_table()).lint(&program);
+    for diagnostic in &diagnostics {
+        emit(diagnostic, ErrorFormat::HUMAN);
+    }
+
+    let mut interpreter = Interpreter::new(&program, args, BufWriter::new(io::stdout()))
+        .unwrap_or_else(|e| report(vec![*e], ErrorFormat::HUMAN));
+    halt(interpreter.run());
+}
+
+fn halt(halt: Halt) -> ! {
+    if let Halt::TRAP(message) = &halt {
+        eprintln!("edge: {}", message);
+    }
+    process::exit(halt.code());
+}
+
+fn fmt(inputs: &[PathBuf], check: bool) {
+    let mut unformatted = false;
+
+    for source_path in inputs {
+        check_extension(source_path, ErrorFormat::HUMAN);
+        let source_code = read_source(source_path, ErrorFormat::HUMAN);
+        let formatted = edge::format(&source_code, source_path.clone())
+            .unwrap_or_else(|e| report(vec![*e], ErrorFormat::HUMAN));
+        if formatted == source_code {
+            continue;
+        }
+
+        if check {
+            let line = source_code
+                .lines()
+                .zip(formatted.lines())
+                .position(|(a, b)| a != b)
+                .unwrap_or_else(|| source_code.lines().count().min(formatted.lines().count()));
+            println!(
+                "{}:{}: file is not formatted",
+                source_path.display(),
+                line + 1
+            );
+            unformatted = true;
+        } else if let Err(e) = fs::write(source_path, formatted) {
+            fail(
+                &format!("Cannot write {}: {}", source_path.display(), e),
+                ErrorFormat::HUMAN,
+            );
+        }
+    }
+
+    if unformatted {
         process::exit(1);
     }
+}
+
+fn dump(args: &CompilerArgs, source_code: String, source_path: PathBuf) {
+    if args.dump_tokens {
+        let mut failed = false;
+        for token in Lexer::new(source_code.clone()) {
+            match token {
+                Ok(token) => println!("{}", token),
+                Err(e) => {
+                    emit(
+                        &Diagnostic::from(e).with_file(&source_path),
+                        args.error_format,
+                    );
+                    failed = true;
+                }
+            }
+        }
+        if failed {
+            process::exit(1);
+        }
+    }
+
+    if !args.dump_ast && !args.dump_symbols {
+        return;
+    }
+
+    let mut program = Parser::new(source_code, source_path)
+        .parse()
+        .unwrap_or_else(|e| report(vec![*e], args.error_format));
+    if args.dump_ast {
+        print!("{}", program);
+    }
+
+    if args.dump_symbols {
+        let mut analyzer = Analyzer::new(!args.lib);
+        if let Err(e) = analyzer.analyze(&mut program) {
+            report(vec![*e], args.error_format);
+        }
+        print!("{}", analyzer.symbol_table());
+    }
+}
+
+fn main() {
+    let matches = CompilerArgs::command().get_matches();
+    let args = CompilerArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    match &args.command {
+        Some(Command::Run { input, args }) => {
+            run(input.clone(), args.clone());
+            return;
+        }
+        Some(Command::Fmt { inputs, check }) => {
+            fmt(inputs, *check);
+            return;
+        }
+        Some(Command::Lsp) => {
+            let code = Server::new().run(io::stdin().lock(), io::stdout().lock());
+            process::exit(code);
+        }
+        None => {}
+    }
 
-    let output_path = args.output.unwrap_or_else(|| {
+    if let Some(code) = &args.explain {
+        match error_codes::explain(code) {
+            Some(explanation) => print!("{}", explanation),
+            None => fail(
+                &format!("'{}' is not a valid error code", code),
+                args.error_format,
+            ),
+        }
+        return;
+    }
+
+    let source_path = args.input.clone().unwrap();
+    check_extension(&source_path, args.error_format);
+
+    let output_path = args.output.clone().unwrap_or_else(|| {
         source_path
             .file_stem()
             .unwrap_or_else(|| "a.out".as_ref())
@@ -35,29 +263,75 @@ fn main() {
             .into()
     });
 
-    let output_path_c = PathBuf::from(format!("{}.c", output_path.display()));
+    if args.lib && args.emit == Some(Emit::BYTECODE) {
+        fail(
+            "--lib cannot be used with --emit=bytecode, bytecode needs a main function",
+            args.error_format,
+        );
+    }
+
+    if args.emit.is_some() && args.backend != Backend::C {
+        fail("--backend cannot be used with --emit", args.error_format);
+    }
+
+    if args.target.is_some() && (args.emit.is_some() || args.backend != Backend::C) {
+        fail(
+            "--target cannot be used with --emit or --backend",
+            args.error_format,
+        );
+    }
+
+    let format = match (args.target, args.emit, args.backend) {
+        (Some(Target::WASM32), _, _) => Format::WASM32,
+        (None, Some(Emit::BYTECODE), _) => Format::BYTECODE,
+        (None, Some(Emit::LLVM), _) => Format::LLVM,
+        (None, None, Backend::C) => Format::C,
+        (None, None, Backend::ASM) => Format::ASM,
+        (None, None, Backend::QBE) => Format::QBE,
+    };
+    let target_path = PathBuf::from(format!("{}.{}", output_path.display(), format.extension()));
 
     if args.debug {
         println!("*** DEBUG MODE ENABLED ***");
         println!("Source file: {}", source_path.display());
-        println!("Target C file: {}", output_path_c.display());
+        println!("Target file: {}", target_path.display());
     }
 
-    let source_code = match fs::read_to_string(&source_path) {
-        Ok(code) => code,
-        Err(e) => {
-            eprintln!("Error reading source file {}: {}", source_path.display(), e);
-            process::exit(1);
-        }
-    };
+    let source_code = read_source(&source_path, args.error_format);
 
-    let mut parser = Parser::new(source_code, output_path_c.to_string_lossy().into_owned());
+    if args.dump_tokens || args.dump_ast || args.dump_symbols {
+        dump(&args, source_code, source_path);
+        return;
+    }
 
     println!("Compiling {}...", source_path.display());
 
     let start_time = Instant::now();
 
-    parser.parse();
+    let options = Options {
+        path: source_path,
+        output: Some(target_path.clone()),
+        format,
+        lib: args.lib,
+        line_info: args.line_info,
+        opt_level: args.opt_level,
+        lints: lint_levels(&matches, args.error_format),
+    };
+    match edge::compile(&source_code, &options) {
+        Ok(compiled) => {
+            for warning in &compiled.warnings {
+                emit(warning, args.error_format);
+            }
+            match compiled.output {
+                Output::TEXT(code) => fs::write(&target_path, code).unwrap(),
+                Output::BYTECODE(bytecode) => {
+                    print!("{}", bytecode.disassemble());
+                    fs::write(&target_path, bytecode.serialize()).unwrap();
+                }
+            }
+        }
+        Err(diagnostics) => report(diagnostics, args.error_format),
+    }
 
     let duration = start_time.elapsed();
     let total_seconds = duration.as_secs();
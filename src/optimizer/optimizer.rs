@@ -0,0 +1,210 @@
+use crate::ast::{Expr, Function, ItemKind, Program, Stmt, StmtKind};
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::interpreter::{c_code_line, checked_binary};
+use crate::symbol_table::ConstType;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+pub struct Optimizer {
+    level: u8,
+    propagate: bool,
+    consts: HashMap<String, i32>,
+    assigned: HashSet<String>,
+    scopes: Vec<HashMap<String, i32>>,
+}
+
+impl Optimizer {
+    pub fn new(level: u8) -> Self {
+        Self {
+            level,
+            propagate: false,
+            consts: HashMap::new(),
+            assigned: HashSet::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn optimize(&mut self, program: &mut Program) -> Result<(), Box<Diagnostic>> {
+        if self.level == 0 {
+            return Ok(());
+        }
+
+        for module in &program.modules {
+            for item in &module.items {
+                if let ItemKind::CONST(constant) = &item.kind
+                    && let ConstType::INT32(n) = constant.value
+                {
+                    self.consts.insert(constant.name.clone(), n);
+                }
+            }
+        }
+
+        for module in &mut program.modules {
+            for item in &mut module.items {
+                if let ItemKind::FUNCTION(func) = &mut item.kind {
+                    self.function(func).map_err(|e| e.with_file(&module.path))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn function(&mut self, func: &mut Function) -> Result<(), Box<Diagnostic>> {
+        self.propagate = self.level >= 2 && c_code_line(&func.body).is_none();
+        self.assigned.clear();
+        Self::collect_assigned(&func.body, &mut self.assigned);
+
+        self.scopes.clear();
+        func.body = self.block(mem::take(&mut func.body))?;
+        Ok(())
+    }
+
+    fn collect_assigned(body: &[Stmt], assigned: &mut HashSet<String>) {
+        for stmt in body {
+            match &stmt.kind {
+                StmtKind::ASSIGN(name, _) => {
+                    assigned.insert(name.clone());
+                }
+                StmtKind::IF(_, then, otherwise) => {
+                    Self::collect_assigned(then, assigned);
+                    Self::collect_assigned(otherwise, assigned);
+                }
+                StmtKind::WHILE(_, body) => Self::collect_assigned(body, assigned),
+                _ => {}
+            }
+        }
+    }
+
+    fn declares(body: &[Stmt]) -> bool {
+        body.iter().any(|s| matches!(s.kind, StmtKind::LET(..)))
+    }
+
+    fn block(&mut self, body: Vec<Stmt>) -> Result<Vec<Stmt>, Box<Diagnostic>> {
+        self.scopes.push(HashMap::new());
+        let mut out = Vec::new();
+
+        for mut stmt in body {
+            let line = stmt.line;
+            match stmt.kind {
+                StmtKind::LET(_, _, None) => {}
+                StmtKind::LET(name, var_type, Some(value)) => {
+                    let value = self.expr(value, line)?;
+                    if let Expr::NUMBER(n) = value
+                        && self.propagate
+                        && !self.assigned.contains(&name)
+                    {
+                        self.scopes.last_mut().unwrap().insert(name, n);
+                        continue;
+                    }
+                    stmt.kind = StmtKind::LET(name, var_type, Some(value));
+                }
+                StmtKind::ASSIGN(name, value) => {
+                    stmt.kind = StmtKind::ASSIGN(name, self.expr(value, line)?);
+                }
+                StmtKind::EXPR(expr) => stmt.kind = StmtKind::EXPR(self.expr(expr, line)?),
+                StmtKind::RETURN(value) => {
+                    stmt.kind = StmtKind::RETURN(value.map(|v| self.expr(v, line)).transpose()?);
+                    out.push(stmt);
+                    break;
+                }
+                StmtKind::IF(cond, then, otherwise) => match self.expr(cond, line)? {
+                    Expr::NUMBER(n) => {
+                        let taken = if n != 0 { then } else { otherwise };
+                        let taken = self.block(taken)?;
+                        if Self::declares(&taken) {
+                            stmt.kind = StmtKind::IF(Expr::NUMBER(1), taken, Vec::new());
+                        } else {
+                            let returns =
+                                matches!(taken.last().map(|s| &s.kind), Some(StmtKind::RETURN(_)));
+                            out.extend(taken);
+                            if returns {
+                                break;
+                            }
+                            continue;
+                        }
+                    }
+                    cond => {
+                        let then = self.block(then)?;
+                        let otherwise = self.block(otherwise)?;
+                        stmt.kind = StmtKind::IF(cond, then, otherwise);
+                    }
+                },
+                StmtKind::WHILE(cond, body) => match self.expr(cond, line)? {
+                    Expr::NUMBER(0) => continue,
+                    cond => stmt.kind = StmtKind::WHILE(cond, self.block(body)?),
+                },
+                StmtKind::CCODE(_) => {}
+            }
+            out.push(stmt);
+        }
+
+        self.scopes.pop();
+        Ok(out)
+    }
+
+    fn overflow(line: usize, expr: String) -> Diagnostic {
+        Diagnostic::new(
+            line,
+            format!("Constant expression overflows an int: {}", expr),
+        )
+        .with_code("E0045")
+    }
+
+    fn expr(&mut self, expr: Expr, line: usize) -> Result<Expr, Box<Diagnostic>> {
+        let expr = match expr {
+            Expr::CONST(name) => match self.consts.get(&name) {
+                Some(n) => Expr::NUMBER(*n),
+                None => Expr::CONST(name),
+            },
+            Expr::VAR(name) => match self.scopes.iter().rev().find_map(|scope| scope.get(&name)) {
+                Some(n) => Expr::NUMBER(*n),
+                None => Expr::VAR(name),
+            },
+            Expr::CALL(name, args) => Expr::CALL(name, self.exprs(args, line)?),
+            Expr::BUILTIN(name, args) => Expr::BUILTIN(name, self.exprs(args, line)?),
+            Expr::NEG(value) => match self.expr(*value, line)? {
+                Expr::NUMBER(n) => match n.checked_neg() {
+                    Some(n) => Expr::NUMBER(n),
+                    None => return Err(Self::overflow(line, format!("-({})", n)).into()),
+                },
+                value => Expr::NEG(Box::new(value)),
+            },
+            Expr::BINARY(op, lhs, rhs) => {
+                let lhs = self.expr(*lhs, line)?;
+                let rhs = self.expr(*rhs, line)?;
+                let (Expr::NUMBER(a), Expr::NUMBER(b)) = (&lhs, &rhs) else {
+                    return Ok(Expr::BINARY(op, Box::new(lhs), Box::new(rhs)));
+                };
+                let (a, b) = (*a, *b);
+
+                match checked_binary(op, a, b) {
+                    Some(n) => Expr::NUMBER(n),
+                    None if b == 0 => {
+                        return Err(Diagnostic::new(
+                            line,
+                            format!(
+                                "Constant expression divides by zero: {} {} 0",
+                                a,
+                                op.symbol()
+                            ),
+                        )
+                        .with_code("E0046")
+                        .into());
+                    }
+                    None => {
+                        return Err(
+                            Self::overflow(line, format!("{} {} {}", a, op.symbol(), b)).into()
+                        );
+                    }
+                }
+            }
+            expr => expr,
+        };
+        Ok(expr)
+    }
+
+    fn exprs(&mut self, exprs: Vec<Expr>, line: usize) -> Result<Vec<Expr>, Box<Diagnostic>> {
+        exprs.into_iter().map(|e| self.expr(e, line)).collect()
+    }
+}
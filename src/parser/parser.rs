@@ -1,9 +1,16 @@
-use crate::codegen::codegen::Codegen;
+use crate::ast::{
+    BinOp, CPart, Comments, Const, Expr, Function, Import, Item, ItemKind, Module, Program, Stmt,
+    StmtKind,
+};
+use crate::diagnostic::Diagnostic;
 use crate::lexer::lexer::Lexer;
-use crate::symbol_table::{FunctionType, SymbolTable, VariableType};
-use crate::tokens::Tokens;
+use crate::lints::lints::is_lint;
+use crate::symbol_table::{ConstType, FunctionType, VariableType};
+use crate::tokens::{Span, Tokens, Trivia};
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::mem;
-use std::process;
+use std::path::{Path, PathBuf};
 
 fn is_same_variant(a: &Tokens, b: &Tokens) -> bool {
     mem::discriminant(a) == mem::discriminant(b)
@@ -20,37 +27,95 @@ fn tok_to_string(a: &Tokens) -> String {
         Tokens::EOF => "end of file".to_string(),
         Tokens::PLUS => "+".to_string(),
         Tokens::MINUS => "-".to_string(),
+        Tokens::STAR => "*".to_string(),
+        Tokens::SLASH => "/".to_string(),
+        Tokens::PERCENT => "%".to_string(),
+        Tokens::EQEQ => "==".to_string(),
+        Tokens::NOTEQ => "!=".to_string(),
+        Tokens::LESS => "<".to_string(),
+        Tokens::LESSEQ => "<=".to_string(),
+        Tokens::GREATER => ">".to_string(),
+        Tokens::GREATEREQ => ">=".to_string(),
         Tokens::DOUBLECOL => "::".to_string(),
+        Tokens::COLON => ":".to_string(),
+        Tokens::COMMA => ",".to_string(),
         Tokens::OPENCURLY => "{".to_string(),
         Tokens::CLOSECURLY => "}".to_string(),
         Tokens::CLOSEPAREN => ")".to_string(),
         Tokens::OPENPAREN => "(".to_string(),
         Tokens::VOID => "void type".to_string(),
         Tokens::INT => "int type".to_string(),
+        Tokens::STR => "str type".to_string(),
+        Tokens::OPENBRACKET => "[".to_string(),
+        Tokens::CLOSEBRACKET => "]".to_string(),
         Tokens::RETURN => "return ".to_string(),
+        Tokens::IF => "if".to_string(),
+        Tokens::ELSE => "else".to_string(),
+        Tokens::WHILE => "while".to_string(),
+        Tokens::CONST => "const".to_string(),
+        Tokens::IMPORT => "import".to_string(),
+        Tokens::MOD => "mod".to_string(),
+        Tokens::PUB => "pub".to_string(),
+        Tokens::HASH => "#".to_string(),
+        Tokens::CBLOCK(_) => "c block".to_string(),
     }
 }
 
+pub fn parse_c_code(text: &str) -> Result<Vec<CPart>, String> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        if start > 0 {
+            parts.push(CPart::TEXT(rest[..start].to_string()));
+        }
+        let after = &rest[start + 2..];
+
+        let Some(end) = after.find('}') else {
+            return Err("Unterminated '${' in c code".to_string());
+        };
+
+        parts.push(CPart::IDENT(after[..end].trim().to_string()));
+        rest = &after[end + 1..];
+    }
+
+    if !rest.is_empty() {
+        parts.push(CPart::TEXT(rest.to_string()));
+    }
+    Ok(parts)
+}
+
 pub struct Parser {
     lexer: Lexer,
-    symbol_table: SymbolTable,
-    codegen: Codegen,
     current_token: Tokens,
-    has_return: bool,
+    current_span: Span,
+    source_path: PathBuf,
+    module: String,
+    imports: Vec<String>,
+    module_paths: HashMap<String, PathBuf>,
+    modules: Vec<Module>,
+    import_stack: Vec<PathBuf>,
+    imported: HashSet<PathBuf>,
+    follow_imports: bool,
 }
 
 impl Parser {
-    pub fn new(source_code: String, out_file_name: String) -> Self {
-        let mut lexer = Lexer::new(source_code);
-
-        let initial_token = lexer.next_token();
+    pub fn new(source_code: String, source_path: PathBuf) -> Self {
+        let lexer = Lexer::new(source_code);
+        let root = fs::canonicalize(&source_path).unwrap_or_else(|_| source_path.clone());
 
         Self {
             lexer,
-            symbol_table: SymbolTable::new(),
-            codegen: Codegen::new(out_file_name),
-            current_token: initial_token,
-            has_return: false,
+            current_token: Tokens::EOF,
+            current_span: Span::default(),
+            source_path,
+            module: String::new(),
+            imports: Vec::new(),
+            module_paths: HashMap::new(),
+            modules: Vec::new(),
+            import_stack: vec![root.clone()],
+            imported: HashSet::from([root]),
+            follow_imports: true,
         }
     }
 
@@ -58,339 +123,842 @@ impl Parser {
         &self.current_token
     }
 
-    fn advance(&mut self) {
-        self.current_token = self.lexer.next_token();
+    fn advance(&mut self) -> Result<(), Box<Diagnostic>> {
+        let token = self.lexer.next_token()?;
+        self.current_token = token.kind;
+        self.current_span = token.span;
+        Ok(())
+    }
+
+    fn error(&self, code: &'static str, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(self.lexer.line, message)
+            .with_code(code)
+            .with_span(self.current_span)
     }
 
-    fn expect(&mut self, expected_tok: &Tokens) -> Tokens {
+    fn expect(&mut self, expected_tok: &Tokens) -> Result<Tokens, Box<Diagnostic>> {
         let current_tok = self.current();
 
-        let matches = if expected_tok == current_tok || is_same_variant(expected_tok, current_tok) {
-            true
-        } else if is_same_variant(expected_tok, current_tok) {
-            true
-        } else {
-            false
-        };
+        let matches = expected_tok == current_tok || is_same_variant(expected_tok, current_tok);
 
         if !matches {
-            eprintln!(
-                "ERROR on line {}: Expected {} but got: {}",
-                self.lexer.line,
-                tok_to_string(expected_tok),
-                tok_to_string(current_tok)
-            );
-            process::exit(1);
+            return Err(self
+                .error(
+                    "E0006",
+                    format!(
+                        "Expected {} but got: {}",
+                        tok_to_string(expected_tok),
+                        tok_to_string(current_tok)
+                    ),
+                )
+                .into());
         }
 
         let consumed_token = current_tok.clone();
-        self.advance();
+        self.advance()?;
 
-        consumed_token
+        Ok(consumed_token)
     }
 
-    fn consume_ident_value(&mut self) -> String {
-        let token = self.expect(&Tokens::IDENT("".to_string()));
+    fn consume_ident_value(&mut self) -> Result<String, Box<Diagnostic>> {
+        let token = self.expect(&Tokens::IDENT("".to_string()))?;
         match token {
-            Tokens::IDENT(s) => s,
+            Tokens::IDENT(s) => Ok(s),
             _ => unreachable!(),
         }
     }
 
-    pub fn parse(&mut self) {
+    fn consume_path(&mut self) -> Result<String, Box<Diagnostic>> {
+        let mut path = self.consume_ident_value()?;
+        while *self.current() == Tokens::DOUBLECOL {
+            self.advance()?;
+            path.push_str("::");
+            path.push_str(&self.consume_ident_value()?);
+        }
+        Ok(path)
+    }
+
+    pub fn parse(&mut self) -> Result<Program, Box<Diagnostic>> {
+        let root = self.parse_root()?;
+        self.modules.push(root);
+
+        Ok(Program {
+            modules: mem::take(&mut self.modules),
+        })
+    }
+
+    pub fn parse_syntax(&mut self) -> Result<Module, Box<Diagnostic>> {
+        self.follow_imports = false;
+        self.parse_root()
+    }
+
+    fn parse_root(&mut self) -> Result<Module, Box<Diagnostic>> {
+        let items = self
+            .parse_file()
+            .map_err(|e| e.with_file(&self.source_path))?;
+        Ok(Module {
+            name: String::new(),
+            path: self.source_path.clone(),
+            imports: mem::take(&mut self.imports),
+            items,
+            comments: self.lexer.take_trivia(),
+        })
+    }
+
+    fn comments(&mut self, leading: Vec<Trivia>) -> Comments {
+        Comments {
+            leading,
+            trailing: self.lexer.take_trailing(),
+            after: Vec::new(),
+        }
+    }
+
+    fn parse_file(&mut self) -> Result<Vec<Item>, Box<Diagnostic>> {
+        self.advance()?;
+        self.parse_items()
+    }
+
+    fn parse_items(&mut self) -> Result<Vec<Item>, Box<Diagnostic>> {
+        let mut items = Vec::new();
+
         while *self.current() != Tokens::EOF {
-            match self.current() {
-                Tokens::IDENT(n) => {
-                    if n == "c_comp_append" {
-                        self.parse_c_com_append();
-                    } else {
-                        self.parse_fn_decl();
+            let leading = self.lexer.take_trivia();
+            let allow = self.parse_attributes()?;
+            let mut line = self.lexer.line;
+            let span = self.current_span;
+            let kind =
+                match self.current() {
+                    Tokens::IDENT(n) => {
+                        if n == "c_comp_append" {
+                            ItemKind::CCODE(self.parse_c_com_append()?)
+                        } else if n == "c" && self.lexer.peek_char() == Some('{') {
+                            let (parts, start) = self.parse_c_block()?;
+                            line = start;
+                            ItemKind::CCODE(parts)
+                        } else {
+                            ItemKind::FUNCTION(self.parse_fn_decl(false)?)
+                        }
+                    }
+                    Tokens::CONST => ItemKind::CONST(self.parse_const_decl(false)?),
+                    Tokens::PUB => {
+                        self.advance()?;
+                        match self.current() {
+                            Tokens::CONST => ItemKind::CONST(self.parse_const_decl(true)?),
+                            Tokens::IDENT(_) => ItemKind::FUNCTION(self.parse_fn_decl(true)?),
+                            _ => {
+                                return Err(self.error(
+                                "E0007",
+                                format!(
+                                    "Expected a function or a constant after pub but got: {}",
+                                    tok_to_string(self.current())
+                                ),
+                            ).into());
+                            }
+                        }
+                    }
+                    Tokens::IMPORT => {
+                        self.advance()?;
+                        let current = self.current();
+                        let path =
+                            match current {
+                                Tokens::STRING(s) => s.clone(),
+                                _ => {
+                                    return Err(self.error(
+                                "E0008",
+                                format!(
+                                    "Expected a file path string after import but got: {}",
+                                    tok_to_string(current)
+                                ),
+                            ).into());
+                                }
+                            };
+                        self.advance()?;
+                        self.expect(&Tokens::SEMICOLON)?;
+                        if self.follow_imports {
+                            self.parse_module(PathBuf::from(&path), line)?;
+                        }
+                        ItemKind::IMPORT(Import::FILE(path))
+                    }
+                    Tokens::MOD => {
+                        self.advance()?;
+                        let name = self.consume_ident_value()?;
+                        self.expect(&Tokens::SEMICOLON)?;
+                        if self.follow_imports {
+                            self.parse_module(PathBuf::from(format!("{}.ed", name)), line)?;
+                        }
+                        ItemKind::IMPORT(Import::MOD(name))
+                    }
+                    _ => {
+                        return Err(self
+                            .error(
+                                "E0009",
+                                format!(
+                                    "Unexpected token in global scope: {}",
+                                    tok_to_string(self.current())
+                                ),
+                            )
+                            .into());
                     }
+                };
+            let comments = self.comments(leading);
+            items.push(Item {
+                kind,
+                line,
+                span,
+                allow,
+                comments,
+            });
+        }
+
+        Ok(items)
+    }
+
+    fn parse_module(&mut self, relative_path: PathBuf, line: usize) -> Result<(), Box<Diagnostic>> {
+        let path = self
+            .source_path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(&relative_path);
+
+        if path.extension().is_none_or(|ext| ext != "ed") {
+            return Err(Diagnostic::new(
+                line,
+                format!(
+                    "Imported file must end with the '.ed' extension. Found: {}",
+                    relative_path.display()
+                ),
+            )
+            .with_code("E0010")
+            .into());
+        }
+
+        let canonical = fs::canonicalize(&path).map_err(|e| {
+            Diagnostic::new(line, format!("Cannot import {}: {}", path.display(), e))
+                .with_code("E0011")
+        })?;
+
+        let name = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        if name.is_empty()
+            || !name.chars().next().unwrap().is_alphabetic()
+            || !name.chars().all(|c| c.is_alphanumeric() || c == '_')
+            || name.contains("__")
+            || name.ends_with('_')
+        {
+            return Err(
+                Diagnostic::new(line, format!("'{}' is not a valid module name", name))
+                    .with_code("E0012")
+                    .into(),
+            );
+        }
+
+        if let Some(existing) = self.module_paths.get(&name)
+            && *existing != canonical
+        {
+            return Err(Diagnostic::new(
+                line,
+                format!(
+                    "Module '{}' is already defined by {}",
+                    name,
+                    existing.display()
+                ),
+            )
+            .with_code("E0013")
+            .into());
+        }
+
+        if let Some(start) = self.import_stack.iter().position(|p| *p == canonical) {
+            let cycle: Vec<String> = self.import_stack[start..]
+                .iter()
+                .chain([&canonical])
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(Diagnostic::new(
+                line,
+                format!("Import cycle detected: {}", cycle.join(" -> ")),
+            )
+            .with_code("E0014")
+            .into());
+        }
+
+        self.imports.push(name.clone());
+
+        if !self.imported.insert(canonical.clone()) {
+            return Ok(());
+        }
+        self.module_paths.insert(name.clone(), canonical.clone());
+
+        let source_code = fs::read_to_string(&path).map_err(|e| {
+            Diagnostic::new(line, format!("Cannot read {}: {}", path.display(), e))
+                .with_code("E0011")
+        })?;
+
+        let outer_lexer = mem::replace(&mut self.lexer, Lexer::new(source_code));
+        let outer_token = mem::replace(&mut self.current_token, Tokens::EOF);
+        let outer_span = mem::take(&mut self.current_span);
+        let outer_path = mem::replace(&mut self.source_path, path.clone());
+        let outer_module = mem::replace(&mut self.module, name);
+        let outer_imports = mem::take(&mut self.imports);
+        self.import_stack.push(canonical);
+
+        let items = self.parse_file().map_err(|e| e.with_file(&path))?;
+
+        self.import_stack.pop();
+        self.source_path = outer_path;
+        let module = Module {
+            name: mem::replace(&mut self.module, outer_module),
+            path,
+            imports: mem::replace(&mut self.imports, outer_imports),
+            items,
+            comments: self.lexer.take_trivia(),
+        };
+        self.modules.push(module);
+        self.lexer = outer_lexer;
+        self.current_token = outer_token;
+        self.current_span = outer_span;
+        Ok(())
+    }
+
+    fn parse_attributes(&mut self) -> Result<Vec<String>, Box<Diagnostic>> {
+        let mut allow = Vec::new();
+
+        while *self.current() == Tokens::HASH {
+            self.advance()?;
+            self.expect(&Tokens::OPENBRACKET)?;
+            match self.current() {
+                Tokens::IDENT(n) if n == "allow" => self.advance()?,
+                current => {
+                    return Err(self
+                        .error(
+                            "E0027",
+                            format!(
+                                "Expected 'allow' in attribute but got: {}",
+                                tok_to_string(current)
+                            ),
+                        )
+                        .into());
                 }
-                _ => {
-                    eprintln!(
-                        "ERROR on line {}: Unexpected token in global scope: {}",
-                        self.lexer.line,
-                        tok_to_string(self.current())
-                    );
-                    process::exit(1);
+            }
+            self.expect(&Tokens::OPENPAREN)?;
+
+            let mut first = true;
+            while *self.current() != Tokens::CLOSEPAREN {
+                if !first {
+                    self.expect(&Tokens::COMMA)?;
+                }
+                first = false;
+
+                if let Tokens::IDENT(lint) = self.current()
+                    && !is_lint(lint)
+                {
+                    return Err(self
+                        .error("E0028", format!("Unknown lint '{}'", lint))
+                        .into());
                 }
+                allow.push(self.consume_ident_value()?);
             }
+
+            self.expect(&Tokens::CLOSEPAREN)?;
+            self.expect(&Tokens::CLOSEBRACKET)?;
         }
-        self.codegen.end();
+
+        Ok(allow)
     }
 
-    fn parse_c_com_append(&mut self) {
-        self.advance();
-        self.expect(&Tokens::OPENPAREN);
+    fn parse_c_com_append(&mut self) -> Result<Vec<CPart>, Box<Diagnostic>> {
+        self.advance()?;
+        self.expect(&Tokens::OPENPAREN)?;
 
         let current = self.current();
-        match current {
-            Tokens::STRING(n) => self.codegen.c_comp_append(n.to_string()),
-            Tokens::IDENT(_n) => todo!(),
+        let parts = match current {
+            Tokens::STRING(n) => {
+                let parts = self.c_code(&n.clone())?;
+                self.advance()?;
+                parts
+            }
+            Tokens::IDENT(_) => vec![CPart::CONST(self.consume_path()?)],
             _ => {
-                eprintln!(
-                    "ERROR on line {}: Expected a string or an ident for c_comp_append but got: {}",
-                    self.lexer.line,
-                    tok_to_string(current)
-                );
-                process::exit(1);
+                return Err(self
+                    .error(
+                        "E0015",
+                        format!(
+                            "Expected a string or an ident for c_comp_append but got: {}",
+                            tok_to_string(current)
+                        ),
+                    )
+                    .into());
             }
+        };
+        self.expect(&Tokens::CLOSEPAREN)?;
+        self.expect(&Tokens::SEMICOLON)?;
+        Ok(parts)
+    }
+
+    fn parse_c_block(&mut self) -> Result<(Vec<CPart>, usize), Box<Diagnostic>> {
+        let block = self.lexer.raw_block()?;
+        let code = block.trim_start_matches(['\r', '\n']);
+        let start = self.lexer.line - block.matches('\n').count()
+            + block[..block.len() - code.len()].matches('\n').count();
+        let parts = self.c_code(code.trim_end())?;
+        self.advance()?;
+        Ok((parts, start))
+    }
+
+    fn c_code(&self, text: &str) -> Result<Vec<CPart>, Box<Diagnostic>> {
+        parse_c_code(text).map_err(|e| self.error("E0016", e).into())
+    }
+
+    fn parse_const_decl(&mut self, public: bool) -> Result<Const, Box<Diagnostic>> {
+        self.expect(&Tokens::CONST)?;
+        let const_name = self.consume_ident_value()?;
+        self.expect(&Tokens::EQUALS)?;
+
+        let const_type = match self.current() {
+            Tokens::NUMBER(n) => ConstType::INT32(*n),
+            Tokens::STRING(s) => ConstType::STRING(s.clone()),
+            _ => {
+                return Err(self
+                    .error(
+                        "E0017",
+                        format!(
+                            "Expected a number or a string for constant '{}' but got: {}",
+                            const_name,
+                            tok_to_string(self.current())
+                        ),
+                    )
+                    .into());
+            }
+        };
+        self.advance()?;
+        self.expect(&Tokens::SEMICOLON)?;
+
+        Ok(Const {
+            name: const_name,
+            public,
+            value: const_type,
+        })
+    }
+
+    fn parse_value(&mut self) -> Result<Expr, Box<Diagnostic>> {
+        let mut value = self.parse_sum()?;
+
+        loop {
+            let op = match self.current() {
+                Tokens::EQEQ => BinOp::EQ,
+                Tokens::NOTEQ => BinOp::NE,
+                Tokens::LESS => BinOp::LT,
+                Tokens::LESSEQ => BinOp::LE,
+                Tokens::GREATER => BinOp::GT,
+                Tokens::GREATEREQ => BinOp::GE,
+                _ => return Ok(value),
+            };
+            self.advance()?;
+            let rhs = self.parse_sum()?;
+            value = Expr::BINARY(op, Box::new(value), Box::new(rhs));
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, Box<Diagnostic>> {
+        let mut value = self.parse_term()?;
+
+        loop {
+            let op = match self.current() {
+                Tokens::PLUS => BinOp::ADD,
+                Tokens::MINUS => BinOp::SUB,
+                _ => return Ok(value),
+            };
+            self.advance()?;
+            let rhs = self.parse_term()?;
+            value = Expr::BINARY(op, Box::new(value), Box::new(rhs));
         }
-        self.advance();
-        self.expect(&Tokens::CLOSEPAREN);
-        self.expect(&Tokens::SEMICOLON);
     }
 
-    fn parse_fn_decl(&mut self) {
-        let func_name = self.consume_ident_value();
-        self.expect(&Tokens::DOUBLECOL);
+    fn parse_term(&mut self) -> Result<Expr, Box<Diagnostic>> {
+        let mut value = self.parse_unary()?;
+
+        loop {
+            let op = match self.current() {
+                Tokens::STAR => BinOp::MUL,
+                Tokens::SLASH => BinOp::DIV,
+                Tokens::PERCENT => BinOp::REM,
+                _ => return Ok(value),
+            };
+            self.advance()?;
+            let rhs = self.parse_unary()?;
+            value = Expr::BINARY(op, Box::new(value), Box::new(rhs));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, Box<Diagnostic>> {
+        if *self.current() == Tokens::MINUS {
+            self.advance()?;
+            return Ok(Expr::NEG(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, Box<Diagnostic>> {
+        let mut value = match self.current() {
+            Tokens::NUMBER(n) => {
+                let value = *n;
+                self.advance()?;
+                Expr::NUMBER(value)
+            }
+            Tokens::STRING(s) => {
+                let value = s.clone();
+                self.advance()?;
+                Expr::STRING(value)
+            }
+            Tokens::IDENT(_) => {
+                let path = self.consume_path()?;
+
+                if *self.current() == Tokens::OPENPAREN {
+                    let args = self.parse_call_args()?;
+                    Expr::CALL(path, args)
+                } else {
+                    Expr::IDENT(path)
+                }
+            }
+            Tokens::OPENPAREN => {
+                self.advance()?;
+                let value = self.parse_value()?;
+                self.expect(&Tokens::CLOSEPAREN)?;
+                value
+            }
+            _ => {
+                return Err(self
+                    .error(
+                        "E0018",
+                        format!(
+                            "Expected a number, a string or an ident but got: {}",
+                            tok_to_string(self.current())
+                        ),
+                    )
+                    .into());
+            }
+        };
+
+        while *self.current() == Tokens::OPENBRACKET {
+            self.advance()?;
+            let index = self.parse_value()?;
+            self.expect(&Tokens::CLOSEBRACKET)?;
+            value = Expr::INDEX(Box::new(value), Box::new(index));
+        }
+
+        Ok(value)
+    }
+
+    fn parse_type(&mut self) -> Result<VariableType, Box<Diagnostic>> {
+        let var_type = match self.current() {
+            Tokens::INT => {
+                self.advance()?;
+                VariableType::INT32
+            }
+            Tokens::STR => {
+                self.advance()?;
+                VariableType::STR
+            }
+            Tokens::OPENBRACKET => {
+                self.advance()?;
+                self.expect(&Tokens::CLOSEBRACKET)?;
+                VariableType::SLICE(Box::new(self.parse_type()?))
+            }
+            _ => {
+                return Err(self
+                    .error(
+                        "E0019",
+                        format!(
+                            "Unknow type: {}, expected 'int', 'str' or '[]<type>'",
+                            tok_to_string(self.current())
+                        ),
+                    )
+                    .into());
+            }
+        };
+        Ok(var_type)
+    }
+
+    fn parse_call_args(&mut self) -> Result<Vec<Expr>, Box<Diagnostic>> {
+        self.expect(&Tokens::OPENPAREN)?;
+
+        let mut args = Vec::new();
+        if *self.current() != Tokens::CLOSEPAREN {
+            args.push(self.parse_value()?);
+            while *self.current() == Tokens::COMMA {
+                self.advance()?;
+                args.push(self.parse_value()?);
+            }
+        }
+
+        self.expect(&Tokens::CLOSEPAREN)?;
+        Ok(args)
+    }
+
+    fn parse_fn_decl(&mut self, public: bool) -> Result<Function, Box<Diagnostic>> {
+        let span = self.current_span;
+        let func_name = self.consume_ident_value()?;
+        self.expect(&Tokens::DOUBLECOL)?;
 
         let current = self.current();
         let func_ret_type = match current {
             Tokens::VOID => {
-                if func_name == "main" {
-                    eprintln!(
-                        "ERROR on line {}: Return type of main must be int got void",
-                        self.lexer.line
-                    );
-                    process::exit(1);
+                if func_name == "main" && self.module.is_empty() {
+                    return Err(self
+                        .error("E0020", "Return type of main must be int got void")
+                        .into());
                 }
                 FunctionType::VOID
             }
             Tokens::INT => FunctionType::INT,
+            Tokens::STR => FunctionType::STR,
             _ => {
-                eprintln!(
-                    "ERROR on line {}: Unknow return type: {}, expected 'void', 'int'",
-                    self.lexer.line,
-                    tok_to_string(current)
-                );
-                process::exit(1);
+                return Err(self
+                    .error(
+                        "E0021",
+                        format!(
+                            "Unknow return type: {}, expected 'void', 'int', 'str'",
+                            tok_to_string(current)
+                        ),
+                    )
+                    .into());
             }
         };
-        self.advance();
+        self.advance()?;
 
-        self.expect(&Tokens::OPENCURLY);
+        let mut param_spans = Vec::new();
+        let params = if *self.current() == Tokens::OPENPAREN {
+            self.parse_params(&mut param_spans)?
+        } else {
+            Vec::new()
+        };
 
-        self.codegen.start_function(&func_name, &func_ret_type);
-        self.symbol_table.set_func(func_name, func_ret_type.clone());
-        self.parse_stmt(func_ret_type.clone());
-        if !self.has_return {
-            eprintln!(
-                "ERROR on line {}: Expected return a the end of a function",
-                self.lexer.line
-            );
-            process::exit(1);
+        self.expect(&Tokens::OPENCURLY)?;
+
+        let body = self.parse_stmt()?;
+        if !matches!(
+            body.last(),
+            Some(Stmt {
+                kind: StmtKind::RETURN(_),
+                ..
+            })
+        ) {
+            return Err(self
+                .error("E0022", "Expected return a the end of a function")
+                .into());
         }
-        self.advance();
+        self.advance()?;
+
+        Ok(Function {
+            name: func_name,
+            public,
+            ret_type: func_ret_type,
+            params,
+            body,
+            span,
+            param_spans,
+        })
     }
 
-    fn parse_stmt(&mut self, func_ret_type: FunctionType) {
+    fn parse_params(
+        &mut self,
+        spans: &mut Vec<Span>,
+    ) -> Result<Vec<(String, VariableType)>, Box<Diagnostic>> {
+        self.expect(&Tokens::OPENPAREN)?;
+
+        let mut params = Vec::new();
+        while *self.current() != Tokens::CLOSEPAREN {
+            if !params.is_empty() {
+                self.expect(&Tokens::COMMA)?;
+            }
+
+            spans.push(self.current_span);
+            let name = self.consume_ident_value()?;
+            self.expect(&Tokens::COLON)?;
+            params.push((name, self.parse_type()?));
+        }
+
+        self.expect(&Tokens::CLOSEPAREN)?;
+        Ok(params)
+    }
+
+    fn parse_stmt(&mut self) -> Result<Vec<Stmt>, Box<Diagnostic>> {
+        let mut body = Vec::new();
+
         loop {
             let token_type = mem::discriminant(self.current());
 
             if token_type == mem::discriminant(&Tokens::CLOSECURLY) {
-                self.codegen.end_function();
                 break;
             }
 
             if token_type == mem::discriminant(&Tokens::EOF) {
-                eprintln!(
-                    "ERROR on line {}: Expected '}}' but reached end of file.",
-                    self.lexer.line
-                );
-                process::exit(1);
+                return Err(self
+                    .error("E0023", "Expected '}' but reached end of file.")
+                    .into());
             }
 
-            match self.current() {
+            let leading = self.lexer.take_trivia();
+            let allow = self.parse_attributes()?;
+            let mut line = self.lexer.line;
+            let mut span = self.current_span;
+            let kind = match self.current() {
                 Tokens::LET => {
-                    self.parse_let_stmt();
+                    let (kind, name_span) = self.parse_let_stmt()?;
+                    span = name_span;
+                    kind
                 }
 
-                Tokens::IDENT(name) => {
-                    let action_name = name.clone();
+                Tokens::IDENT(name) if name == "c_comp_append" => {
+                    StmtKind::CCODE(self.parse_c_com_append()?)
+                }
+
+                Tokens::IDENT(name) if name == "c" && self.lexer.peek_char() == Some('{') => {
+                    let (parts, start) = self.parse_c_block()?;
+                    line = start;
+                    StmtKind::CCODE(parts)
+                }
 
-                    self.advance();
+                Tokens::IDENT(_) => {
+                    let action_name = self.consume_path()?;
 
                     match self.current() {
-                        Tokens::EQUALS => {
-                            self.parse_var_reassign(action_name);
-                        }
-                        Tokens::OPENPAREN => {
-                            self.parse_func_call(action_name);
-                        }
+                        Tokens::EQUALS => self.parse_var_reassign(action_name)?,
+                        Tokens::OPENPAREN => self.parse_func_call(action_name)?,
                         _ => {
-                            eprintln!(
-                                "ERROR on line {}: Expected '=' or '(' but got: {}",
-                                self.lexer.line,
-                                tok_to_string(self.current())
-                            );
-                            process::exit(1);
+                            return Err(self
+                                .error(
+                                    "E0024",
+                                    format!(
+                                        "Expected '=' or '(' but got: {}",
+                                        tok_to_string(self.current())
+                                    ),
+                                )
+                                .into());
                         }
                     }
                 }
+                Tokens::IF => self.parse_if()?,
+                Tokens::WHILE => {
+                    self.advance()?;
+                    let cond = self.parse_value()?;
+                    StmtKind::WHILE(cond, self.parse_block()?)
+                }
                 Tokens::RETURN => {
-                    match func_ret_type {
-                        FunctionType::INT => {
-                            self.advance();
-                            let curr = self.current();
-                            match curr {
-                                Tokens::NUMBER(n) => {
-                                    self.codegen.return_stmt(n.to_string());
-                                }
-                                Tokens::IDENT(n) => match self.symbol_table.get_var(n) {
-                                    Ok(t) => {
-                                        if mem::discriminant(&t)
-                                            != mem::discriminant(&VariableType::INT32(
-                                                0.to_string(),
-                                            ))
-                                        {
-                                            eprintln!("ERROR on line {}: Cannot return a non int var from a int function", self.lexer.line);
-                                            process::exit(1);
-                                        } else {
-                                            self.codegen.return_stmt(n.to_string());
-                                        }
-                                    }
-                                    Err(e) => {
-                                        eprintln!("ERROR on line {}: {}", self.lexer.line, e);
-                                        process::exit(1);
-                                    }
-                                },
-                                _ => {
-                                    eprintln!("ERROR on line {}: Expected a number or an ident at the end of a int returning function but got: {}", self.lexer.line, tok_to_string(curr));
-                                    process::exit(1);
-                                }
-                            }
-                        }
-                        FunctionType::VOID => self.codegen.return_stmt("".to_string()),
-                    }
-                    self.advance();
-                    self.expect(&Tokens::SEMICOLON);
-
-                    let curr = self.current();
-                    match curr {
-                        Tokens::CLOSECURLY => {
-                            self.has_return = true;
-                            self.codegen.end_function();
-                            break;
-                        }
-                        _ => {
-                            eprintln!(
-                                "ERROR at line {}: Useless stmts after return stmt",
-                                self.lexer.line
-                            );
-                            process::exit(1);
-                        }
+                    self.advance()?;
+                    let value = if *self.current() == Tokens::SEMICOLON {
+                        None
+                    } else {
+                        Some(self.parse_value()?)
+                    };
+                    self.expect(&Tokens::SEMICOLON)?;
+
+                    if *self.current() != Tokens::CLOSECURLY {
+                        return Err(self
+                            .error("E0025", "Useless stmts after return stmt")
+                            .into());
                     }
+                    StmtKind::RETURN(value)
                 }
                 _ => {
-                    eprintln!(
-                        "ERROR on line {}: Unexpected token in function scope: {}",
-                        self.lexer.line,
-                        tok_to_string(self.current())
-                    );
-
-                    self.advance();
-                    process::exit(1);
+                    return Err(self
+                        .error(
+                            "E0026",
+                            format!(
+                                "Unexpected token in function scope: {}",
+                                tok_to_string(self.current())
+                            ),
+                        )
+                        .into());
                 }
-            }
+            };
+            let comments = self.comments(leading);
+            body.push(Stmt {
+                kind,
+                line,
+                span,
+                allow,
+                comments,
+            });
         }
+
+        if let Some(last) = body.last_mut() {
+            last.comments.after = self.lexer.take_trivia();
+        }
+        Ok(body)
     }
 
-    fn parse_func_call(&mut self, name: String) {
-        if name == "putchar".to_string() {
-            self.expect(&Tokens::OPENPAREN);
-            match self.current() {
-                Tokens::NUMBER(n) => {
-                    let value = n.clone();
-                    self.advance();
-                    self.expect(&Tokens::CLOSEPAREN);
-                    self.codegen.function_call(name, value.to_string());
-                }
-                _ => {
-                    eprintln!(
-                        "ERROR at line {}: Expected a number value for function putchar got: {}",
-                        self.lexer.line,
-                        tok_to_string(self.current())
-                    );
-                    process::exit(1);
-                }
+    fn parse_block(&mut self) -> Result<Vec<Stmt>, Box<Diagnostic>> {
+        self.expect(&Tokens::OPENCURLY)?;
+        let body = self.parse_stmt()?;
+        self.expect(&Tokens::CLOSECURLY)?;
+        Ok(body)
+    }
+
+    fn parse_if(&mut self) -> Result<StmtKind, Box<Diagnostic>> {
+        self.expect(&Tokens::IF)?;
+        let cond = self.parse_value()?;
+        let then = self.parse_block()?;
+
+        let otherwise = if *self.current() == Tokens::ELSE {
+            self.advance()?;
+            if *self.current() == Tokens::IF {
+                let line = self.lexer.line;
+                let span = self.current_span;
+                vec![Stmt {
+                    kind: self.parse_if()?,
+                    line,
+                    span,
+                    allow: Vec::new(),
+                    comments: Comments::default(),
+                }]
+            } else {
+                self.parse_block()?
             }
         } else {
-            eprintln!(
-                "ERROR at line {}: Unknow function: {}",
-                self.lexer.line, name
-            );
-            process::exit(1);
-        }
-        self.expect(&Tokens::SEMICOLON);
+            Vec::new()
+        };
+
+        Ok(StmtKind::IF(cond, then, otherwise))
     }
 
-    fn parse_var_reassign(&mut self, name: String) {
-        self.expect(&Tokens::EQUALS);
-        let var = self.symbol_table.get_var(&name);
+    fn parse_func_call(&mut self, name: String) -> Result<StmtKind, Box<Diagnostic>> {
+        let args = self.parse_call_args()?;
+        self.expect(&Tokens::SEMICOLON)?;
+        Ok(StmtKind::EXPR(Expr::CALL(name, args)))
+    }
 
-        match var {
-            Ok(declared_type) => match declared_type {
-                VariableType::INT32(_) => match self.current() {
-                    Tokens::NUMBER(v) => {
-                        let var_type = VariableType::INT32((*v).to_string());
-                        self.codegen.var_reassign(&name, &var_type);
-                        self.advance();
-                    }
-                    _ => {
-                        eprintln!(
-                            "ERROR on line {}: Expected a number or an ident but got: {}",
-                            self.lexer.line,
-                            tok_to_string(self.current())
-                        );
-                        process::exit(1);
-                    }
-                },
-            },
-            Err(e) => {
-                eprintln!("ERROR on line {}: {}", self.lexer.line, e);
-                process::exit(1);
-            }
-        }
-        self.expect(&Tokens::SEMICOLON);
+    fn parse_var_reassign(&mut self, name: String) -> Result<StmtKind, Box<Diagnostic>> {
+        self.expect(&Tokens::EQUALS)?;
+        let value = self.parse_value()?;
+        self.expect(&Tokens::SEMICOLON)?;
+        Ok(StmtKind::ASSIGN(name, value))
     }
 
-    fn parse_let_stmt(&mut self) {
-        self.expect(&Tokens::LET);
-        let var_name = self.consume_ident_value();
-        self.expect(&Tokens::EQUALS);
+    fn parse_let_stmt(&mut self) -> Result<(StmtKind, Span), Box<Diagnostic>> {
+        self.expect(&Tokens::LET)?;
+        let span = self.current_span;
+        let var_name = self.consume_ident_value()?;
 
-        let var_type: VariableType;
-        match self.current() {
-            Tokens::NUMBER(n) => {
-                var_type = VariableType::INT32((*n).to_string());
-                self.advance();
-            }
-            Tokens::IDENT(n) => match self.symbol_table.get_var(n) {
-                Ok(t) => {
-                    var_type = match t {
-                        VariableType::INT32(_) => VariableType::INT32(n.to_string()),
-                    };
-                    self.advance();
-                }
-                Err(e) => {
-                    eprintln!("ERROR on line {}: {}", self.lexer.line, e);
-                    process::exit(1);
-                }
-            },
-            _ => {
-                eprintln!(
-                    "ERROR on line {}: Expected a number or expression but got: {}",
-                    self.lexer.line,
-                    tok_to_string(self.current())
-                );
-                process::exit(1);
-            }
+        let var_type = if *self.current() == Tokens::COLON {
+            self.advance()?;
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        if var_type.is_some() && *self.current() == Tokens::SEMICOLON {
+            self.advance()?;
+            return Ok((StmtKind::LET(var_name, var_type, None), span));
         }
+        self.expect(&Tokens::EQUALS)?;
+
+        let value = self.parse_value()?;
 
-        self.expect(&Tokens::SEMICOLON);
-        self.codegen.let_stmt(&var_name, &var_type);
-        self.symbol_table.set_var(var_name, var_type);
+        self.expect(&Tokens::SEMICOLON)?;
+        Ok((StmtKind::LET(var_name, var_type, Some(value)), span))
     }
 }
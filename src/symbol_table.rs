@@ -1,38 +1,200 @@
-use std::collections::HashMap;
+use crate::tokens::Span;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum VariableType {
-    INT32(String),
+    INT32,
+    STR,
+    SLICE(Box<VariableType>),
+}
+
+impl fmt::Display for VariableType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VariableType::INT32 => write!(f, "int"),
+            VariableType::STR => write!(f, "str"),
+            VariableType::SLICE(inner) => write!(f, "[]{}", inner),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum FunctionType {
     VOID,
     INT,
+    STR,
+}
+
+impl fmt::Display for FunctionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FunctionType::VOID => write!(f, "void"),
+            FunctionType::INT => write!(f, "int"),
+            FunctionType::STR => write!(f, "str"),
+        }
+    }
+}
+
+impl FunctionType {
+    pub fn value_type(&self) -> Option<VariableType> {
+        match self {
+            FunctionType::VOID => None,
+            FunctionType::INT => Some(VariableType::INT32),
+            FunctionType::STR => Some(VariableType::STR),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ConstType {
+    INT32(i32),
+    STRING(String),
+}
+
+impl fmt::Display for ConstType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstType::INT32(n) => write!(f, "{}", n),
+            ConstType::STRING(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+pub fn params_to_string(params: &[(String, VariableType)]) -> String {
+    params
+        .iter()
+        .map(|(name, param_type)| format!("{}: {}", name, param_type))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn qualify(module: &str, name: &str) -> String {
+    if module.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}::{}", module, name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Binding {
+    pub name: String,
+    pub module: String,
+    pub span: Span,
+    pub var_type: VariableType,
+    pub param: bool,
+    pub reads: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scope {
+    pub module: String,
+    pub start: usize,
+    pub bindings: Vec<usize>,
 }
 
 pub struct SymbolTable {
-    vars: HashMap<String, VariableType>,
+    vars: Vec<HashMap<String, usize>>,
+    bindings: Vec<Binding>,
+    scopes: Vec<Scope>,
+    calls: HashMap<String, usize>,
     functions: HashMap<String, FunctionType>,
+    func_params: HashMap<String, Vec<(String, VariableType)>>,
+    consts: HashMap<String, ConstType>,
+    public: HashSet<String>,
+    imports: HashMap<String, HashSet<String>>,
 }
 
 impl SymbolTable {
     pub fn new() -> Self {
         SymbolTable {
-            vars: HashMap::new(),
+            vars: vec![HashMap::new()],
+            bindings: Vec::new(),
+            scopes: Vec::new(),
+            calls: HashMap::new(),
             functions: HashMap::new(),
+            func_params: HashMap::new(),
+            consts: HashMap::new(),
+            public: HashSet::new(),
+            imports: HashMap::new(),
         }
     }
 
     pub fn get_var(&self, name: &str) -> Result<VariableType, String> {
         self.vars
-            .get(name)
-            .cloned()
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .map(|index| self.bindings[*index].var_type.clone())
             .ok_or_else(|| format!("Variable '{}' not in scope", name))
     }
 
-    pub fn set_var(&mut self, name: String, value: VariableType) {
-        self.vars.insert(name, value);
+    pub fn set_var(&mut self, binding: Binding) {
+        let index = self.bindings.len();
+        self.vars
+            .last_mut()
+            .unwrap()
+            .insert(binding.name.clone(), index);
+        self.bindings.push(binding);
+    }
+
+    pub fn read_var(&mut self, name: &str) {
+        if let Some(index) = self.vars.iter().rev().find_map(|scope| scope.get(name)) {
+            self.bindings[*index].reads += 1;
+        }
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    pub fn mark_scope(&mut self, module: &str, start: usize) {
+        let mut bindings: Vec<usize> = self
+            .vars
+            .iter()
+            .flat_map(|scope| scope.values().copied())
+            .collect();
+        bindings.sort();
+        self.scopes.push(Scope {
+            module: module.to_string(),
+            start,
+            bindings,
+        });
+    }
+
+    pub fn scope_at(&self, module: &str, from: usize, offset: usize) -> Vec<&Binding> {
+        self.scopes
+            .iter()
+            .filter(|scope| scope.module == module && (from..=offset).contains(&scope.start))
+            .max_by_key(|scope| scope.start)
+            .map_or_else(Vec::new, |scope| {
+                scope
+                    .bindings
+                    .iter()
+                    .map(|index| &self.bindings[*index])
+                    .collect()
+            })
+    }
+
+    pub fn call_func(&mut self, name: &str) {
+        *self.calls.entry(name.to_string()).or_default() += 1;
+    }
+
+    pub fn func_calls(&self, name: &str) -> usize {
+        self.calls.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn clear_vars(&mut self) {
+        self.vars = vec![HashMap::new()];
+    }
+
+    pub fn push_scope(&mut self) {
+        self.vars.push(HashMap::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.vars.pop();
     }
 
     pub fn get_func(&self, name: &str) -> Result<FunctionType, String> {
@@ -42,7 +204,92 @@ impl SymbolTable {
             .ok_or_else(|| format!("Function '{}' is not defined", name))
     }
 
+    pub fn functions(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.functions.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
     pub fn set_func(&mut self, name: String, value: FunctionType) {
         self.functions.insert(name, value);
     }
+
+    pub fn get_func_params(&self, name: &str) -> Vec<(String, VariableType)> {
+        self.func_params.get(name).cloned().unwrap_or_default()
+    }
+
+    pub fn set_func_params(&mut self, name: String, params: Vec<(String, VariableType)>) {
+        self.func_params.insert(name, params);
+    }
+
+    pub fn get_const(&self, name: &str) -> Result<ConstType, String> {
+        self.consts
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Constant '{}' is not defined", name))
+    }
+
+    pub fn consts(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.consts.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    pub fn set_const(&mut self, name: String, value: ConstType) {
+        self.consts.insert(name, value);
+    }
+
+    pub fn set_public(&mut self, name: String) {
+        self.public.insert(name);
+    }
+
+    pub fn is_public(&self, name: &str) -> bool {
+        self.public.contains(name)
+    }
+
+    pub fn add_import(&mut self, module: String, imported: String) {
+        self.imports.entry(module).or_default().insert(imported);
+    }
+
+    pub fn has_import(&self, module: &str, imported: &str) -> bool {
+        self.imports
+            .get(module)
+            .is_some_and(|imports| imports.contains(imported))
+    }
+}
+
+impl fmt::Display for SymbolTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let visibility = |name: &str| if self.is_public(name) { "pub " } else { "" };
+
+        let mut modules: Vec<_> = self.imports.iter().collect();
+        modules.sort_by_key(|(module, _)| *module);
+        for (module, imports) in modules {
+            let mut imports: Vec<_> = imports.iter().map(String::as_str).collect();
+            imports.sort();
+            let module = if module.is_empty() { "<root>" } else { module };
+            writeln!(f, "module {} imports {}", module, imports.join(", "))?;
+        }
+
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by_key(|(name, _)| *name);
+        for (name, ret_type) in functions {
+            writeln!(
+                f,
+                "{}fn {} :: {}({})",
+                visibility(name),
+                name,
+                ret_type,
+                params_to_string(&self.get_func_params(name))
+            )?;
+        }
+
+        let mut consts: Vec<_> = self.consts.iter().collect();
+        consts.sort_by_key(|(name, _)| *name);
+        for (name, value) in consts {
+            writeln!(f, "{}const {} = {}", visibility(name), name, value)?;
+        }
+
+        Ok(())
+    }
 }
@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Tokens {
     LET,
@@ -8,13 +10,73 @@ pub enum Tokens {
     SEMICOLON,
     PLUS,
     MINUS,
+    STAR,
+    SLASH,
+    PERCENT,
+    EQEQ,
+    NOTEQ,
+    LESS,
+    LESSEQ,
+    GREATER,
+    GREATEREQ,
     DOUBLECOL,
+    COLON,
+    COMMA,
     OPENCURLY,
     CLOSECURLY,
     OPENPAREN,
     CLOSEPAREN,
+    OPENBRACKET,
+    CLOSEBRACKET,
     VOID,
     INT,
+    STR,
     RETURN,
+    IF,
+    ELSE,
+    WHILE,
+    CONST,
+    IMPORT,
+    MOD,
+    PUB,
+    HASH,
+    CBLOCK(String),
     EOF,
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trivia {
+    COMMENT(String),
+    TRAILING(String),
+    BLANKLINE,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: Tokens,
+    pub span: Span,
+    pub lexeme: String,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{} {}..{} {:?} {:?}",
+            self.span.line,
+            self.span.column,
+            self.span.start,
+            self.span.end,
+            self.kind,
+            self.lexeme
+        )
+    }
+}